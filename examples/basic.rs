@@ -1,12 +1,12 @@
 //! An example of how to use the macros in the `keypad` driver crate.
 //!
 //! This uses mock types that implement the `embeddded-hal` traits without using
-//! any real hardware. It will compile and run on your host computer, but it
-//! won't do anything interesting because there are no real buttons to press.
+//! any real hardware. It will compile and run on your host computer. There are
+//! no real buttons to press, so it presses a simulated key instead.
 
 use core::convert::Infallible;
 use embedded_hal::digital::v2::InputPin;
-use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Output, PullUp, GPIOA};
+use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
 use keypad::{keypad_new, keypad_struct};
 
 // Define the struct that represents your keypad matrix. Give the specific pins
@@ -32,9 +32,25 @@ keypad_struct! {
     }
 }
 
+// The simulated circuit board that the mock pins are connected through.
+static WIRING: Wiring = Wiring::new();
+
 fn main() {
     // Get access to (mock) general-purpose input/output pins.
-    let pins = GPIOA::split();
+    let pins = GPIOA::split_wired(&WIRING);
+
+    // Describe which pins are the rows and columns of the simulated keypad, so
+    // we can press its keys.
+    let matrix = WIRING.matrix(
+        [pins.pa0.id(), pins.pa1.id(), pins.pa2.id(), pins.pa3.id()],
+        [
+            pins.pa4.id(),
+            pins.pa5.id(),
+            pins.pa6.id(),
+            pins.pa7.id(),
+            pins.pa8.id(),
+        ],
+    );
 
     // Create an instance of the keypad struct you defined above.
     let keypad = keypad_new!(ExampleKeypad {
@@ -61,8 +77,8 @@ fn main() {
     let first_key = &keys[0][0];
     println!("Is first key pressed? {:?}\n", first_key.is_low());
 
-    // Print a table of which keys are pressed. This is a boring example because
-    // we have no way to press the mock keys and they'll always stay unpressed.
+    // Press a simulated key, and print a table of which keys are pressed.
+    matrix.press(2, 3);
 
     for (row_index, row) in keys.iter().enumerate() {
         print!("row {}: ", row_index);
@@ -0,0 +1,338 @@
+//! Perkins-style chorded braille entry.
+//!
+//! A braille keyboard has one key for each of the six dots in a braille cell,
+//! plus a space key and a backspace key. To type a cell, you press all the keys
+//! for its dots at the same time, and the cell is entered once every key of the
+//! chord has been released again. The keys don't need to be pressed or
+//! released at exactly the same moment, so the chord is made of every key that
+//! was held down at some point since the first press.
+//!
+//! [`ChordEngine`] does that bookkeeping. You tell it which keypad positions
+//! are the braille keys with a [`BrailleKeys`] layout, feed it the
+//! [`KeyEvent`]s from your keypad scans, and it gives you a [`BrailleInput`]
+//! each time a chord is finished. A [`BrailleTable`] can then translate cells
+//! into text.
+//!
+//! ```
+//! use keypad::braille::{BrailleCell, BrailleInput, BrailleKeys, BrailleTable, ChordEngine};
+//! use keypad::{KeyEvent, Position};
+//!
+//! // One row of keys, laid out like a Perkins brailler:
+//! // dots 3 2 1, space, dots 4 5 6, and backspace on the far right.
+//! let layout = BrailleKeys {
+//!     dots: [
+//!         Position::new(0, 2),
+//!         Position::new(0, 1),
+//!         Position::new(0, 0),
+//!         Position::new(0, 4),
+//!         Position::new(0, 5),
+//!         Position::new(0, 6),
+//!     ],
+//!     space: Some(Position::new(0, 3)),
+//!     backspace: Some(Position::new(0, 7)),
+//! };
+//! let mut engine = ChordEngine::new(layout);
+//!
+//! // Type the letter "d" (dots 1, 4 and 5), releasing the keys one by one.
+//! let events = [
+//!     KeyEvent::Pressed(Position::new(0, 2)),
+//!     KeyEvent::Pressed(Position::new(0, 4)),
+//!     KeyEvent::Pressed(Position::new(0, 5)),
+//!     KeyEvent::Released(Position::new(0, 4)),
+//!     KeyEvent::Released(Position::new(0, 2)),
+//! ];
+//! for event in events.iter() {
+//!     assert_eq!(engine.update(*event), None);
+//! }
+//! let input = engine.update(KeyEvent::Released(Position::new(0, 5)));
+//!
+//! let cell = BrailleCell::from_dots(&[1, 4, 5]);
+//! assert_eq!(input, Some(BrailleInput::Cell(cell)));
+//! assert_eq!(cell.to_char(), '⠙');
+//! assert_eq!(BrailleTable::ENGLISH_LETTERS.lookup(cell), Some('d'));
+//! ```
+
+use crate::{KeyEvent, Position};
+
+/// The dots that are raised in one six-dot braille cell.
+///
+/// Bit `n - 1` is set if dot `n` is raised, which is the same order used by
+/// the Unicode braille patterns block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BrailleCell(u8);
+
+impl BrailleCell {
+    /// A cell with no raised dots.
+    pub const EMPTY: BrailleCell = BrailleCell(0);
+
+    /// Create a cell from a bitmask of raised dots. Bits above the sixth are
+    /// ignored.
+    pub const fn new(bits: u8) -> Self {
+        BrailleCell(bits & 0b11_1111)
+    }
+
+    /// Create a cell from a list of raised dot numbers, from 1 to 6.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dot number is outside that range.
+    pub fn from_dots(dots: &[u8]) -> Self {
+        let mut bits = 0;
+        for &dot in dots {
+            assert!((1..=6).contains(&dot), "braille dots are numbered 1 to 6");
+            bits |= 1 << (dot - 1);
+        }
+        BrailleCell(bits)
+    }
+
+    /// The bitmask of raised dots.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Is dot `dot` (numbered 1 to 6) raised?
+    pub fn has_dot(self, dot: u8) -> bool {
+        (1..=6).contains(&dot) && self.0 & (1 << (dot - 1)) != 0
+    }
+
+    /// Is no dot raised?
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The Unicode braille pattern character for this cell, from U+2800 to
+    /// U+283F.
+    pub fn to_char(self) -> char {
+        // Every value in this range is a valid char.
+        char::from_u32(0x2800 + u32::from(self.0)).unwrap_or('\u{2800}')
+    }
+}
+
+/// Which keypad positions are used as the keys of a braille keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrailleKeys {
+    /// The keys for dots 1 through 6, in that order.
+    pub dots: [Position; 6],
+    /// The space key, if there is one.
+    pub space: Option<Position>,
+    /// The backspace key, if there is one.
+    pub backspace: Option<Position>,
+}
+
+/// A finished chord, produced once all of its keys are released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BrailleInput {
+    /// Only dot keys were pressed.
+    Cell(BrailleCell),
+    /// Only the space key was pressed.
+    Space,
+    /// The space key was pressed together with some dot keys. Braille note
+    /// takers usually treat these chords as commands.
+    Command(BrailleCell),
+    /// The backspace key was pressed. Any other keys in the same chord are
+    /// ignored.
+    Backspace,
+}
+
+const SPACE_BIT: u8 = 1 << 6;
+const BACKSPACE_BIT: u8 = 1 << 7;
+
+/// Turns key events into braille chords.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug, Clone)]
+pub struct ChordEngine {
+    layout: BrailleKeys,
+    /// The keys that are currently held down, using the same bits as `chord`.
+    held: u8,
+    /// Every key that has been pressed since the chord started. Bits 0-5 are
+    /// the dots, followed by space and backspace.
+    chord: u8,
+}
+
+impl ChordEngine {
+    /// Create a new `ChordEngine` using the given key layout.
+    pub fn new(layout: BrailleKeys) -> Self {
+        Self {
+            layout,
+            held: 0,
+            chord: 0,
+        }
+    }
+
+    /// The key layout this engine was created with.
+    pub fn layout(&self) -> &BrailleKeys {
+        &self.layout
+    }
+
+    /// The keys pressed so far in the chord that's being entered, or `None`
+    /// if no chord has been started.
+    pub fn pending(&self) -> Option<BrailleInput> {
+        Self::decode(self.chord)
+    }
+
+    /// Forget the chord that's being entered, if any.
+    pub fn reset(&mut self) {
+        self.held = 0;
+        self.chord = 0;
+    }
+
+    /// Handle one key event, and return the finished chord if this event
+    /// released its last key. Events for keys that aren't part of the layout
+    /// are ignored.
+    ///
+    /// ```
+    /// use keypad::braille::{BrailleCell, BrailleInput, BrailleKeys, ChordEngine};
+    /// use keypad::{KeyEvent, Position};
+    ///
+    /// // Dots 1 to 6 are columns 0 to 5, followed by space and backspace.
+    /// let key = |column| Position::new(0, column);
+    /// let press = |column| KeyEvent::Pressed(key(column));
+    /// let release = |column| KeyEvent::Released(key(column));
+    /// let mut engine = ChordEngine::new(BrailleKeys {
+    ///     dots: [key(0), key(1), key(2), key(3), key(4), key(5)],
+    ///     space: Some(key(6)),
+    ///     backspace: Some(key(7)),
+    /// });
+    ///
+    /// assert_eq!(engine.update(press(6)), None);
+    /// assert_eq!(engine.update(release(6)), Some(BrailleInput::Space));
+    ///
+    /// // Space with dots 1 and 3 is a command, whichever key is released
+    /// // last.
+    /// let command = BrailleInput::Command(BrailleCell::from_dots(&[1, 3]));
+    /// engine.update(press(6));
+    /// engine.update(press(0));
+    /// engine.update(press(2));
+    /// assert_eq!(engine.update(release(6)), None);
+    /// assert_eq!(engine.update(release(0)), None);
+    /// assert_eq!(engine.update(release(2)), Some(command));
+    ///
+    /// // Backspace ignores the dots pressed with it.
+    /// engine.update(press(1));
+    /// engine.update(press(7));
+    /// assert_eq!(engine.update(release(7)), None);
+    /// assert_eq!(engine.update(release(1)), Some(BrailleInput::Backspace));
+    ///
+    /// // A chord isn't entered while any of its keys is still held, and keys
+    /// // pressed in the meantime join it.
+    /// engine.update(press(0));
+    /// engine.update(press(1));
+    /// assert_eq!(engine.update(release(0)), None);
+    /// assert_eq!(engine.update(press(3)), None);
+    /// assert_eq!(engine.update(release(1)), None);
+    /// let cell = BrailleInput::Cell(BrailleCell::from_dots(&[1, 2, 4]));
+    /// assert_eq!(engine.pending(), Some(cell));
+    /// assert_eq!(engine.update(release(3)), Some(cell));
+    /// assert_eq!(engine.pending(), None);
+    /// ```
+    pub fn update(&mut self, event: KeyEvent) -> Option<BrailleInput> {
+        let bit = self.bit(event.position())?;
+        if event.is_press() {
+            self.held |= bit;
+            self.chord |= bit;
+            return None;
+        }
+
+        self.held &= !bit;
+        if self.held != 0 {
+            return None;
+        }
+        let input = Self::decode(self.chord);
+        self.chord = 0;
+        input
+    }
+
+    /// Find the chord bit for the key at this position.
+    fn bit(&self, position: Position) -> Option<u8> {
+        if let Some(index) = self.layout.dots.iter().position(|&dot| dot == position) {
+            Some(1 << index)
+        } else if self.layout.space == Some(position) {
+            Some(SPACE_BIT)
+        } else if self.layout.backspace == Some(position) {
+            Some(BACKSPACE_BIT)
+        } else {
+            None
+        }
+    }
+
+    fn decode(chord: u8) -> Option<BrailleInput> {
+        let cell = BrailleCell::new(chord);
+        if chord & BACKSPACE_BIT != 0 {
+            Some(BrailleInput::Backspace)
+        } else if chord & SPACE_BIT != 0 {
+            if cell.is_empty() {
+                Some(BrailleInput::Space)
+            } else {
+                Some(BrailleInput::Command(cell))
+            }
+        } else if cell.is_empty() {
+            None
+        } else {
+            Some(BrailleInput::Cell(cell))
+        }
+    }
+}
+
+/// A table for translating braille cells into text characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrailleTable<'a> {
+    entries: &'a [(BrailleCell, char)],
+}
+
+impl<'a> BrailleTable<'a> {
+    /// The 26 letters of uncontracted English braille, in lowercase.
+    pub const ENGLISH_LETTERS: BrailleTable<'static> = BrailleTable::new(&[
+        (BrailleCell::new(0b00_0001), 'a'),
+        (BrailleCell::new(0b00_0011), 'b'),
+        (BrailleCell::new(0b00_1001), 'c'),
+        (BrailleCell::new(0b01_1001), 'd'),
+        (BrailleCell::new(0b01_0001), 'e'),
+        (BrailleCell::new(0b00_1011), 'f'),
+        (BrailleCell::new(0b01_1011), 'g'),
+        (BrailleCell::new(0b01_0011), 'h'),
+        (BrailleCell::new(0b00_1010), 'i'),
+        (BrailleCell::new(0b01_1010), 'j'),
+        (BrailleCell::new(0b00_0101), 'k'),
+        (BrailleCell::new(0b00_0111), 'l'),
+        (BrailleCell::new(0b00_1101), 'm'),
+        (BrailleCell::new(0b01_1101), 'n'),
+        (BrailleCell::new(0b01_0101), 'o'),
+        (BrailleCell::new(0b00_1111), 'p'),
+        (BrailleCell::new(0b01_1111), 'q'),
+        (BrailleCell::new(0b01_0111), 'r'),
+        (BrailleCell::new(0b00_1110), 's'),
+        (BrailleCell::new(0b01_1110), 't'),
+        (BrailleCell::new(0b10_0101), 'u'),
+        (BrailleCell::new(0b10_0111), 'v'),
+        (BrailleCell::new(0b11_1010), 'w'),
+        (BrailleCell::new(0b10_1101), 'x'),
+        (BrailleCell::new(0b11_1101), 'y'),
+        (BrailleCell::new(0b11_0101), 'z'),
+    ]);
+
+    /// Create a table from a list of cells and the characters they stand for.
+    /// If a cell appears more than once, the first entry is used.
+    pub const fn new(entries: &'a [(BrailleCell, char)]) -> Self {
+        Self { entries }
+    }
+
+    /// The character for this cell, if the table has one.
+    pub fn lookup(&self, cell: BrailleCell) -> Option<char> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == cell)
+            .map(|&(_, c)| c)
+    }
+
+    /// The text character for a finished chord: the table's character for a
+    /// cell, or a space for the space key. Backspace and commands don't have a
+    /// character.
+    pub fn translate(&self, input: BrailleInput) -> Option<char> {
+        match input {
+            BrailleInput::Cell(cell) => self.lookup(cell),
+            BrailleInput::Space => Some(' '),
+            BrailleInput::Command(_) | BrailleInput::Backspace => None,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Keypads where every pin is both a row and a column.
+//!
+//! In a charlieplexed, or "round robin", keypad matrix, N pins can read
+//! N×(N-1) keys. There's one key for every ordered pair of different pins, in
+//! series with a diode. To read the key at row `r` and column `c`, pin `c` is
+//! switched to an output and set low, pin `r` is read as an input with a
+//! pull-up resistor, and then pin `c` is switched back to an input. The diode
+//! makes sure that the key only pulls pin `r` low when pin `c` is the one
+//! being driven, and not the other way around.
+//!
+//! The pins must be switchable between input and output modes at runtime,
+//! through the `IoPin` trait. The keys on the diagonal, where the row and the
+//! column are the same pin, don't exist and always read as unpressed.
+//!
+//! ```
+//! use keypad::charlieplex::Charlieplex;
+//! use keypad::embedded_hal::digital::v2::InputPin;
+//! use keypad::mock_hal::{ErasedPin, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+//! use keypad::{Position, Scan};
+//!
+//! type Keypad = Charlieplex<ErasedPin<Input<PullUp>>, ErasedPin<Output<OpenDrain>>, 3>;
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let pins = GPIOA::split_wired(&WIRING);
+//! let ids = [pins.pa0.id(), pins.pa1.id(), pins.pa2.id()];
+//! let mut keypad = Keypad::new([
+//!     pins.pa0.into_pull_up_input().erase(),
+//!     pins.pa1.into_pull_up_input().erase(),
+//!     pins.pa2.into_pull_up_input().erase(),
+//! ]);
+//!
+//! // The key at row 2, column 0 connects pins 2 and 0. The mock wiring has no
+//! // diodes, so it also looks like the key at row 0, column 2 is pressed.
+//! WIRING.connect(ids[2], ids[0]);
+//! assert!(keypad.key(2, 0).unwrap().is_low().unwrap());
+//! assert!(keypad.key(2, 1).unwrap().is_high().unwrap());
+//! assert!(keypad.key(1, 1).is_none());
+//!
+//! let state = keypad.scan().unwrap();
+//! let pressed: Vec<Position> = state.pressed_keys().collect();
+//! assert_eq!(pressed, [Position::new(0, 2), Position::new(2, 0)]);
+//! ```
+
+use crate::io_line::Line;
+pub use crate::io_line::LineError;
+use crate::{KeyState, Position, Scan};
+use core::cell::RefCell;
+use embedded_hal::digital::v2::{InputPin, IoPin, OutputPin};
+
+/// A charlieplexed keypad with `N` pins, which implements
+/// [`Scan<N, N>`](crate::Scan).
+///
+/// `I` is the type of the pins in input mode, with pull-up resistors, and `O`
+/// is their type in output mode. Like the struct generated by
+/// [`keypad_struct!`](crate::keypad_struct), the pins are stored in `RefCell`s
+/// so that the virtual key pins from [`key()`](Charlieplex::key) can switch
+/// them through a shared reference. Reading keys is not reentrant.
+///
+/// If switching a pin between modes fails, the HAL doesn't give the pin back.
+/// The error is returned, and any later attempt to use that pin returns
+/// [`LineError::Lost`].
+///
+/// ```
+/// use keypad::charlieplex::{Charlieplex, LineError};
+/// use keypad::embedded_hal::digital::v2::InputPin;
+/// use keypad::mock_hal::{ErasedPin, Faulty, GpioExt, Input, MockError, OpenDrain, Output, PullUp};
+/// use keypad::mock_hal::{Schedule, Wiring, GPIOA};
+///
+/// type Pin<MODE> = Faulty<ErasedPin<MODE>, MockError>;
+/// type Keypad = Charlieplex<Pin<Input<PullUp>>, Pin<Output<OpenDrain>>, 3>;
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let ids = [pins.pa0.id(), pins.pa1.id(), pins.pa2.id()];
+/// let faulty = |pin: ErasedPin<Input<PullUp>>, schedule| Faulty::new(pin, MockError::Injected, schedule);
+/// let keypad = Keypad::new([
+///     faulty(pins.pa0.into_pull_up_input().erase(), Schedule::Never),
+///     // Pin 1 fails the first time it's read.
+///     faulty(pins.pa1.into_pull_up_input().erase(), Schedule::Nth(1)),
+///     // Pin 2 fails the fifth time it's used, which is when it's switched
+///     // back to an input during the second scan.
+///     faulty(pins.pa2.into_pull_up_input().erase(), Schedule::Nth(5)),
+/// ]);
+///
+/// // Reading pin 1 while pin 0 is driven fails, but pin 0 is released
+/// // anyway, so it doesn't pull pin 2 low through the key between them.
+/// assert_eq!(keypad.scan(), Err(LineError::Pin(MockError::Injected)));
+/// WIRING.connect(ids[2], ids[0]);
+/// assert!(keypad.key(2, 1).unwrap().is_high().unwrap());
+///
+/// // Switching pin 2 back to an input fails, so the pin is lost, and every
+/// // later use of it returns an error.
+/// assert_eq!(keypad.scan(), Err(LineError::Pin(MockError::Injected)));
+/// assert_eq!(keypad.scan(), Err(LineError::Lost));
+/// assert_eq!(keypad.key(2, 0).unwrap().is_low(), Err(LineError::Lost));
+/// ```
+pub struct Charlieplex<I, O, const N: usize> {
+    pins: [RefCell<Line<I, O>>; N],
+}
+
+impl<I, O, E, const N: usize> Charlieplex<I, O, N>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    /// Create a new keypad from pins that are already inputs with pull-up
+    /// resistors.
+    pub fn new(pins: [I; N]) -> Self {
+        Self {
+            pins: pins.map(|pin| RefCell::new(Line::Input(pin))),
+        }
+    }
+
+    /// Get a virtual `embedded-hal` input pin for the key at this row and
+    /// column, or `None` if they're the same pin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row or column is `N` or more.
+    pub fn key(&self, row: usize, column: usize) -> Option<CharlieplexInput<'_, I, O, N>> {
+        assert!(row < N && column < N, "key position out of range");
+        if row == column {
+            None
+        } else {
+            Some(CharlieplexInput {
+                keypad: self,
+                row,
+                column,
+            })
+        }
+    }
+
+    /// Read the state of every key, driving each pin low in turn.
+    ///
+    /// Like [`KeypadInput`](crate::KeypadInput), every pin is switched back
+    /// to an input even if reading fails. If both fail, the read error is
+    /// returned.
+    pub fn scan(&self) -> Result<KeyState<N, N>, LineError<E>> {
+        let mut state = KeyState::new();
+        for column in 0..N {
+            self.drive_low(column)?;
+            let out = self.read_column(column, &mut state);
+            let restored = self.release(column);
+            out?;
+            restored?;
+        }
+        Ok(state)
+    }
+
+    fn read_column(&self, column: usize, state: &mut KeyState<N, N>) -> Result<(), LineError<E>> {
+        for row in (0..N).filter(|&row| row != column) {
+            state.set(Position::new(row, column), self.read_low(row)?);
+        }
+        Ok(())
+    }
+
+    /// Read a pin that's currently an input.
+    fn read_low(&self, index: usize) -> Result<bool, LineError<E>> {
+        self.pins[index].borrow().read_low()
+    }
+
+    /// Switch a pin from an input to an output, and set it low.
+    fn drive_low(&self, index: usize) -> Result<(), LineError<E>> {
+        self.pins[index].borrow_mut().drive_low()
+    }
+
+    /// Switch a pin from an output back to an input.
+    fn release(&self, index: usize) -> Result<(), LineError<E>> {
+        self.pins[index].borrow_mut().release()
+    }
+}
+
+impl<I, O, E, const N: usize> Scan<N, N> for Charlieplex<I, O, N>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    type Error = LineError<E>;
+
+    fn scan(&mut self) -> Result<KeyState<N, N>, LineError<E>> {
+        Charlieplex::scan(self)
+    }
+}
+
+/// A virtual `embedded-hal` input pin representing one key of a
+/// [`Charlieplex`] keypad.
+///
+/// Reading it switches the key's column pin to a low output, reads its row
+/// pin, and switches the column pin back to an input. Like
+/// [`KeypadInput`](crate::KeypadInput), it's not reentrant.
+pub struct CharlieplexInput<'a, I, O, const N: usize> {
+    keypad: &'a Charlieplex<I, O, N>,
+    row: usize,
+    column: usize,
+}
+
+impl<'a, I, O, E, const N: usize> InputPin for CharlieplexInput<'a, I, O, N>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    type Error = LineError<E>;
+
+    /// Read the state of the key at this row and column. Not reentrant.
+    fn is_high(&self) -> Result<bool, LineError<E>> {
+        Ok(!self.is_low()?)
+    }
+
+    /// Read the state of the key at this row and column. Not reentrant.
+    fn is_low(&self) -> Result<bool, LineError<E>> {
+        self.keypad.drive_low(self.column)?;
+        let out = self.keypad.read_low(self.row);
+        let restored = self.keypad.release(self.column);
+        let out = out?;
+        restored?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,254 @@
+//! Dual-tone multi-frequency (DTMF) dialing tones for telephone keypads.
+//!
+//! Each key of a standard 4x4 telephone keypad is identified by a pair of
+//! tones: a low frequency for its row, and a high frequency for its column.
+//!
+//! |            | 1209 Hz | 1336 Hz | 1477 Hz | 1633 Hz |
+//! |------------|---------|---------|---------|---------|
+//! | **697 Hz** | 1       | 2       | 3       | A       |
+//! | **770 Hz** | 4       | 5       | 6       | B       |
+//! | **852 Hz** | 7       | 8       | 9       | C       |
+//! | **941 Hz** | *       | 0       | #       | D       |
+//!
+//! [`DtmfGenerator`] synthesizes those tones as signed 16-bit PCM samples,
+//! using only integer math and a small lookup table. Feed it the key events
+//! from your keypad, and keep asking it to [`fill()`](DtmfGenerator::fill)
+//! buffers for your DAC or I2S peripheral. It plays the tone for as long as
+//! the key is held, and silence otherwise.
+//!
+//! ```
+//! use keypad::dtmf::{DtmfGenerator, DtmfTone};
+//! use keypad::{KeyEvent, Position};
+//!
+//! let mut generator = DtmfGenerator::new(8000);
+//!
+//! generator.update(KeyEvent::Pressed(Position::new(1, 1)));
+//! assert_eq!(generator.tone(), DtmfTone::from_char('5'));
+//! assert_eq!(generator.tone().unwrap().frequencies(), (770, 1336));
+//!
+//! let mut buffer = [0i16; 160];
+//! generator.fill(&mut buffer);
+//! assert!(buffer.iter().any(|&sample| sample > 8000));
+//!
+//! generator.update(KeyEvent::Released(Position::new(1, 1)));
+//! generator.fill(&mut buffer);
+//! assert!(buffer.iter().all(|&sample| sample == 0));
+//! ```
+
+use crate::{KeyEvent, Position};
+
+/// The low tone frequencies of each row, in Hz.
+pub const ROW_FREQUENCIES: [u16; 4] = [697, 770, 852, 941];
+
+/// The high tone frequencies of each column, in Hz.
+pub const COLUMN_FREQUENCIES: [u16; 4] = [1209, 1336, 1477, 1633];
+
+/// The characters printed on a standard telephone keypad, by row and column.
+pub const KEYS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// One full period of a sine wave, with an amplitude of `i16::MAX`.
+const SINE: [i16; 256] = [
+    0, 804, 1608, 2410, 3212, 4011, 4808, 5602, 6393, 7179, 7962, 8739, 9512, 10278, 11039, 11793,
+    12539, 13279, 14010, 14732, 15446, 16151, 16846, 17530, 18204, 18868, 19519, 20159, 20787,
+    21403, 22005, 22594, 23170, 23731, 24279, 24811, 25329, 25832, 26319, 26790, 27245, 27683,
+    28105, 28510, 28898, 29268, 29621, 29956, 30273, 30571, 30852, 31113, 31356, 31580, 31785,
+    31971, 32137, 32285, 32412, 32521, 32609, 32678, 32728, 32757, 32767, 32757, 32728, 32678,
+    32609, 32521, 32412, 32285, 32137, 31971, 31785, 31580, 31356, 31113, 30852, 30571, 30273,
+    29956, 29621, 29268, 28898, 28510, 28105, 27683, 27245, 26790, 26319, 25832, 25329, 24811,
+    24279, 23731, 23170, 22594, 22005, 21403, 20787, 20159, 19519, 18868, 18204, 17530, 16846,
+    16151, 15446, 14732, 14010, 13279, 12539, 11793, 11039, 10278, 9512, 8739, 7962, 7179, 6393,
+    5602, 4808, 4011, 3212, 2410, 1608, 804, 0, -804, -1608, -2410, -3212, -4011, -4808, -5602,
+    -6393, -7179, -7962, -8739, -9512, -10278, -11039, -11793, -12539, -13279, -14010, -14732,
+    -15446, -16151, -16846, -17530, -18204, -18868, -19519, -20159, -20787, -21403, -22005, -22594,
+    -23170, -23731, -24279, -24811, -25329, -25832, -26319, -26790, -27245, -27683, -28105, -28510,
+    -28898, -29268, -29621, -29956, -30273, -30571, -30852, -31113, -31356, -31580, -31785, -31971,
+    -32137, -32285, -32412, -32521, -32609, -32678, -32728, -32757, -32767, -32757, -32728, -32678,
+    -32609, -32521, -32412, -32285, -32137, -31971, -31785, -31580, -31356, -31113, -30852, -30571,
+    -30273, -29956, -29621, -29268, -28898, -28510, -28105, -27683, -27245, -26790, -26319, -25832,
+    -25329, -24811, -24279, -23731, -23170, -22594, -22005, -21403, -20787, -20159, -19519, -18868,
+    -18204, -17530, -16846, -16151, -15446, -14732, -14010, -13279, -12539, -11793, -11039, -10278,
+    -9512, -8739, -7962, -7179, -6393, -5602, -4808, -4011, -3212, -2410, -1608, -804,
+];
+
+/// One of the 16 DTMF tone pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DtmfTone {
+    row: u8,
+    column: u8,
+}
+
+impl DtmfTone {
+    /// The tone for the key at this position of a standard 4x4 telephone
+    /// keypad, or `None` if the position is outside of it.
+    pub fn from_position(position: Position) -> Option<Self> {
+        if position.row < 4 && position.column < 4 {
+            Some(Self {
+                row: position.row as u8,
+                column: position.column as u8,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The tone for the key labeled with this character, or `None` if no key
+    /// has that label. Letters may be upper or lower case.
+    pub fn from_char(c: char) -> Option<Self> {
+        let c = c.to_ascii_uppercase();
+        KEYS.iter().enumerate().find_map(|(row, keys)| {
+            keys.iter()
+                .position(|&key| key == c)
+                .and_then(|column| Self::from_position(Position::new(row, column)))
+        })
+    }
+
+    /// The position of this tone's key on a standard telephone keypad.
+    pub fn position(&self) -> Position {
+        Position::new(usize::from(self.row), usize::from(self.column))
+    }
+
+    /// The label of this tone's key on a standard telephone keypad.
+    pub fn to_char(&self) -> char {
+        KEYS[usize::from(self.row)][usize::from(self.column)]
+    }
+
+    /// The low and high frequencies of this tone, in Hz.
+    pub fn frequencies(&self) -> (u16, u16) {
+        (
+            ROW_FREQUENCIES[usize::from(self.row)],
+            COLUMN_FREQUENCIES[usize::from(self.column)],
+        )
+    }
+}
+
+/// A sine wave oscillator using a 32-bit phase accumulator.
+#[derive(Debug, Clone, Default)]
+struct Oscillator {
+    phase: u32,
+    step: u32,
+}
+
+impl Oscillator {
+    fn new(frequency: u16, sample_rate: u32) -> Self {
+        Self {
+            phase: 0,
+            step: ((u64::from(frequency) << 32) / u64::from(sample_rate)) as u32,
+        }
+    }
+
+    fn next_sample(&mut self) -> i32 {
+        let sample = SINE[(self.phase >> 24) as usize];
+        self.phase = self.phase.wrapping_add(self.step);
+        i32::from(sample)
+    }
+}
+
+/// Generates PCM samples of the DTMF tone for the key that's held down.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug, Clone)]
+pub struct DtmfGenerator {
+    sample_rate: u32,
+    amplitude: i16,
+    tone: Option<DtmfTone>,
+    low: Oscillator,
+    high: Oscillator,
+}
+
+impl DtmfGenerator {
+    /// Create a new, silent generator for the given sample rate in Hz.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sample rate isn't above 3266 Hz, which is needed to
+    /// represent the highest tone.
+    pub fn new(sample_rate: u32) -> Self {
+        assert!(
+            sample_rate > 2 * u32::from(COLUMN_FREQUENCIES[3]),
+            "sample rate is too low for DTMF tones"
+        );
+        Self {
+            sample_rate,
+            amplitude: i16::MAX / 2,
+            tone: None,
+            low: Oscillator::default(),
+            high: Oscillator::default(),
+        }
+    }
+
+    /// Set the peak amplitude of the generated samples. It defaults to half of
+    /// the full scale.
+    pub fn with_amplitude(mut self, amplitude: i16) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// The sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The tone that's playing, if any.
+    pub fn tone(&self) -> Option<DtmfTone> {
+        self.tone
+    }
+
+    /// Start playing a tone from the beginning of its waveform.
+    pub fn start(&mut self, tone: DtmfTone) {
+        let (low, high) = tone.frequencies();
+        self.tone = Some(tone);
+        self.low = Oscillator::new(low, self.sample_rate);
+        self.high = Oscillator::new(high, self.sample_rate);
+    }
+
+    /// Stop playing and output silence.
+    pub fn stop(&mut self) {
+        self.tone = None;
+    }
+
+    /// Start playing when a key of a standard 4x4 keypad is pressed, and stop
+    /// when that key is released. Releasing any other key doesn't stop the
+    /// tone, and pressing another key switches to its tone.
+    pub fn update(&mut self, event: KeyEvent) {
+        let tone = match DtmfTone::from_position(event.position()) {
+            Some(tone) => tone,
+            None => return,
+        };
+        if event.is_press() {
+            self.start(tone);
+        } else if self.tone == Some(tone) {
+            self.stop();
+        }
+    }
+
+    /// Fill the buffer with signed samples, centered on 0.
+    pub fn fill(&mut self, buffer: &mut [i16]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+
+    /// Fill the buffer with unsigned samples, centered on 32768, for DACs that
+    /// don't take signed values. Shift the samples right to fit DACs with
+    /// fewer than 16 bits.
+    pub fn fill_unsigned(&mut self, buffer: &mut [u16]) {
+        for sample in buffer.iter_mut() {
+            *sample = (i32::from(self.next_sample()) + 32768) as u16;
+        }
+    }
+
+    fn next_sample(&mut self) -> i16 {
+        if self.tone.is_none() {
+            return 0;
+        }
+        // The sum of the two sines is at most 2 * i16::MAX, so this scales the
+        // peak to roughly `amplitude`.
+        let sum = self.low.next_sample() + self.high.next_sample();
+        ((sum * i32::from(self.amplitude)) >> 16) as i16
+    }
+}
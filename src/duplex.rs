@@ -0,0 +1,174 @@
+//! Duplex matrices, with two keys at every row and column intersection.
+//!
+//! In a duplex (or "Japanese duplex") matrix, every intersection of a row
+//! and a column has two keys, each in series with a diode, pointing in
+//! opposite directions. The first pass of a scan drives each column low in
+//! turn and reads the rows, which finds the keys whose diodes point towards
+//! the columns. The second pass drives each row low in turn and reads the
+//! columns, which finds the others. That doubles the number of keys for the
+//! same pins, so R rows and C columns read 2×R×C keys.
+//!
+//! Both passes are merged into one grid of `ROWS` rows and `2 * COLUMNS`
+//! columns. The key found at row `r` and column `c` in the first pass is at
+//! column `2 * c`, and the one found in the second pass is at column
+//! `2 * c + 1`.
+//!
+//! Every pin must be switchable between input and output modes at runtime,
+//! through the `IoPin` trait, like the pins of a
+//! [`Charlieplex`](crate::charlieplex::Charlieplex).
+//!
+//! ```
+//! use keypad::duplex::Duplex;
+//! use keypad::mock_hal::{ErasedPin, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+//! use keypad::{Position, Scan};
+//!
+//! type Keypad = Duplex<ErasedPin<Input<PullUp>>, ErasedPin<Output<OpenDrain>>, 2, 2, 4>;
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let pins = GPIOA::split_wired(&WIRING);
+//! let row_ids = [pins.pa0.id(), pins.pa1.id()];
+//! let column_ids = [pins.pa2.id(), pins.pa3.id()];
+//! let mut keypad = Keypad::new(
+//!     [pins.pa0.into_pull_up_input().erase(), pins.pa1.into_pull_up_input().erase()],
+//!     [pins.pa2.into_pull_up_input().erase(), pins.pa3.into_pull_up_input().erase()],
+//! );
+//!
+//! // The mock wiring has no diodes, so connecting row 1 to column 0 looks
+//! // like both keys at that intersection are pressed.
+//! WIRING.connect(row_ids[1], column_ids[0]);
+//! let state = keypad.scan().unwrap();
+//! let pressed: Vec<Position> = state.pressed_keys().collect();
+//! assert_eq!(pressed, [Position::new(1, 0), Position::new(1, 1)]);
+//! ```
+
+use crate::io_line::Line;
+pub use crate::io_line::LineError;
+use crate::{KeyState, Position, Scan};
+use embedded_hal::digital::v2::{InputPin, IoPin, OutputPin};
+
+/// A duplex matrix with `ROWS` rows and `COLUMNS` columns, which implements
+/// [`Scan<ROWS, KEY_COLUMNS>`](crate::Scan), where `KEY_COLUMNS` must be
+/// `2 * COLUMNS`.
+///
+/// `I` is the type of the pins in input mode, with pull-up resistors, and `O`
+/// is their type in output mode. If switching a pin between modes fails, the
+/// HAL doesn't give the pin back. The error is returned, and any later
+/// attempt to use that pin returns [`LineError::Lost`].
+///
+/// ```
+/// use keypad::duplex::{Duplex, LineError};
+/// use keypad::mock_hal::{ErasedPin, Faulty, GpioExt, Input, MockError, OpenDrain, Output, PullUp};
+/// use keypad::mock_hal::{Schedule, Wiring, GPIOA};
+///
+/// type Pin<MODE> = Faulty<ErasedPin<MODE>, MockError>;
+/// type Keypad = Duplex<Pin<Input<PullUp>>, Pin<Output<OpenDrain>>, 1, 1, 2>;
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let ids = [pins.pa0.id(), pins.pa1.id()];
+/// let mut keypad = Keypad::new(
+///     // The row fails the first time it's read.
+///     [Faulty::new(pins.pa0.into_pull_up_input().erase(), MockError::Injected, Schedule::Nth(1))],
+///     // The column fails the sixth time it's used, which is when it's
+///     // switched to an output during the third scan.
+///     [Faulty::new(pins.pa1.into_pull_up_input().erase(), MockError::Injected, Schedule::Nth(6))],
+/// );
+///
+/// // Reading the row fails, but the column is switched back to an input
+/// // anyway, so the next scan works.
+/// assert_eq!(keypad.scan(), Err(LineError::Pin(MockError::Injected)));
+/// WIRING.connect(ids[0], ids[1]);
+/// assert_eq!(keypad.scan().unwrap().count(), 2);
+///
+/// // Switching the column to an output fails, so it's lost, and every later
+/// // scan returns an error.
+/// assert_eq!(keypad.scan(), Err(LineError::Pin(MockError::Injected)));
+/// assert_eq!(keypad.scan(), Err(LineError::Lost));
+/// ```
+pub struct Duplex<I, O, const ROWS: usize, const COLUMNS: usize, const KEY_COLUMNS: usize> {
+    rows: [Line<I, O>; ROWS],
+    columns: [Line<I, O>; COLUMNS],
+}
+
+impl<I, O, E, const ROWS: usize, const COLUMNS: usize, const KEY_COLUMNS: usize>
+    Duplex<I, O, ROWS, COLUMNS, KEY_COLUMNS>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    /// Create a new duplex matrix from pins that are already inputs with
+    /// pull-up resistors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `KEY_COLUMNS` isn't `2 * COLUMNS`.
+    pub fn new(rows: [I; ROWS], columns: [I; COLUMNS]) -> Self {
+        assert!(
+            KEY_COLUMNS == 2 * COLUMNS,
+            "a duplex matrix has two key columns per column"
+        );
+        Self {
+            rows: rows.map(Line::Input),
+            columns: columns.map(Line::Input),
+        }
+    }
+
+    /// Read the state of every key, first driving each column low in turn,
+    /// and then each row.
+    ///
+    /// Like [`KeypadInput`](crate::KeypadInput), every pin is switched back
+    /// to an input even if reading fails. If both fail, the read error is
+    /// returned.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, KEY_COLUMNS>, LineError<E>> {
+        let mut state = KeyState::new();
+        for column in 0..COLUMNS {
+            self.columns[column].drive_low()?;
+            let out = read_lines(&self.rows, |row, low| {
+                state.set(Position::new(row, 2 * column), low)
+            });
+            let restored = self.columns[column].release();
+            out?;
+            restored?;
+        }
+        for row in 0..ROWS {
+            self.rows[row].drive_low()?;
+            let out = read_lines(&self.columns, |column, low| {
+                state.set(Position::new(row, 2 * column + 1), low)
+            });
+            let restored = self.rows[row].release();
+            out?;
+            restored?;
+        }
+        Ok(state)
+    }
+}
+
+/// Read every line, and pass each one's index and whether it's low to `f`.
+fn read_lines<I, O, E>(
+    lines: &[Line<I, O>],
+    mut f: impl FnMut(usize, bool),
+) -> Result<(), LineError<E>>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    for (index, line) in lines.iter().enumerate() {
+        f(index, line.read_low()?);
+    }
+    Ok(())
+}
+
+impl<I, O, E, const ROWS: usize, const COLUMNS: usize, const KEY_COLUMNS: usize>
+    Scan<ROWS, KEY_COLUMNS> for Duplex<I, O, ROWS, COLUMNS, KEY_COLUMNS>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    type Error = LineError<E>;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, KEY_COLUMNS>, LineError<E>> {
+        Duplex::scan(self)
+    }
+}
@@ -0,0 +1,492 @@
+//! Keypads connected through I2C I/O expanders.
+//!
+//! A 4x4 keypad "backpack" usually connects the keypad's rows and columns to
+//! a PCF8574 or MCP23017 I/O expander, and the expander to the
+//! microcontroller's I2C bus. These drivers scan the matrix through the
+//! expander's port registers, and implement [`Scan`](crate::Scan), so they
+//! can be used with an [`EventTracker`](crate::EventTracker) just like a
+//! GPIO keypad.
+//!
+//! Both expanders have an interrupt output. After
+//! [`arm_interrupt()`](Pcf8574::arm_interrupt), every column is held low
+//! between scans, so pressing any key asserts the interrupt, and the
+//! firmware only has to scan when it sees it. If the interrupt output is
+//! connected to an input pin given to
+//! [`with_interrupt()`](Pcf8574::with_interrupt), `scan()` checks it first,
+//! and doesn't use the I2C bus at all while it's deasserted.
+
+use crate::{KeyState, Position, Scan};
+use core::convert::Infallible;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+/// The interrupt pin type of a driver without an interrupt pin. It always
+/// reads as asserted, so every scan reads the expander.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NoInterrupt;
+
+impl InputPin for NoInterrupt {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(false)
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+}
+
+/// Is this active-low interrupt pin asserted? If reading it fails, it's
+/// treated as asserted, so the keypad is scanned anyway.
+fn asserted<INT: InputPin>(interrupt: &INT) -> bool {
+    interrupt.is_low().unwrap_or(true)
+}
+
+/// A keypad whose rows are connected to pins P0, P1, ... of a PCF8574, and
+/// whose columns are connected to the next pins.
+///
+/// The PCF8574's pins are quasi-bidirectional: writing a 1 leaves a pin high
+/// through a weak pull-up, so it can be read, and writing a 0 drives it low.
+/// There can be at most 8 rows and columns in total.
+///
+/// ```
+/// use keypad::expander::Pcf8574;
+/// use keypad::mock_hal::MockPcf8574;
+/// use keypad::{EventTracker, KeyEvent, Position};
+///
+/// let mut keypad: Pcf8574<_, _, 4, 4> = Pcf8574::new(MockPcf8574::new(0x20), 0x20).unwrap();
+/// let mut tracker = EventTracker::new();
+///
+/// // Press the key at row 1, column 2, which connects P1 and P6.
+/// keypad.i2c().connect(1, 6);
+/// let mut events = tracker.update(keypad.scan().unwrap());
+/// assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(1, 2))));
+/// assert_eq!(events.next(), None);
+///
+/// // With the interrupt armed, pressing a key asserts it.
+/// keypad.i2c().disconnect(1, 6);
+/// keypad.arm_interrupt().unwrap();
+/// assert!(!keypad.i2c().interrupt());
+/// keypad.i2c().connect(3, 4);
+/// assert!(keypad.i2c().interrupt());
+/// assert!(keypad.scan().unwrap().is_pressed(Position::new(3, 0)));
+/// ```
+///
+/// With the interrupt output connected to an input pin, scans while it's
+/// deasserted return the last state without using the bus.
+///
+/// ```
+/// use keypad::expander::Pcf8574;
+/// use keypad::mock_hal::MockPcf8574;
+/// use keypad::Position;
+///
+/// let expander = MockPcf8574::new(0x20);
+/// let mut keypad: Pcf8574<_, _, 4, 4> = Pcf8574::new(&expander, 0x20)
+///     .unwrap()
+///     .with_interrupt(expander.interrupt_pin());
+/// keypad.arm_interrupt().unwrap();
+/// expander.connect(1, 6);
+/// assert!(keypad.scan().unwrap().is_pressed(Position::new(1, 2)));
+///
+/// // Nothing changed, so the bus isn't touched.
+/// let transfers = expander.transfers();
+/// assert!(keypad.scan().unwrap().is_pressed(Position::new(1, 2)));
+/// assert_eq!(expander.transfers(), transfers);
+///
+/// // Releasing the key asserts the interrupt, so the next scan reads it.
+/// expander.disconnect(1, 6);
+/// assert!(!keypad.scan().unwrap().any());
+/// assert!(expander.transfers() > transfers);
+/// ```
+///
+/// If a read fails, the column being scanned is released before the error
+/// is returned.
+///
+/// ```
+/// use keypad::expander::Pcf8574;
+/// use keypad::mock_hal::{MockError, MockPcf8574, Schedule};
+///
+/// let expander = MockPcf8574::new(0x20);
+/// let mut keypad: Pcf8574<_, _, 4, 4> = Pcf8574::new(&expander, 0x20).unwrap();
+/// expander.fail_reads(Schedule::Nth(2));
+/// assert_eq!(keypad.scan(), Err(MockError::Injected));
+/// assert_eq!(expander.latch(), 0xFF);
+/// ```
+#[derive(Debug)]
+pub struct Pcf8574<I2C, INT, const ROWS: usize, const COLUMNS: usize> {
+    i2c: I2C,
+    address: u8,
+    armed: bool,
+    interrupt: INT,
+    /// The state found by the last scan.
+    state: KeyState<ROWS, COLUMNS>,
+    /// Is `state` still right as long as the interrupt stays deasserted?
+    fresh: bool,
+}
+
+impl<I2C, E, const ROWS: usize, const COLUMNS: usize> Pcf8574<I2C, NoInterrupt, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + Read<Error = E>,
+{
+    /// Create a new driver for the expander at this address, and release
+    /// every pin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 8 rows and columns in total.
+    pub fn new(i2c: I2C, address: u8) -> Result<Self, E> {
+        assert!(
+            ROWS + COLUMNS <= 8,
+            "too many rows and columns for a PCF8574"
+        );
+        let mut keypad = Self {
+            i2c,
+            address,
+            armed: false,
+            interrupt: NoInterrupt,
+            state: KeyState::new(),
+            fresh: false,
+        };
+        keypad.idle()?;
+        Ok(keypad)
+    }
+}
+
+impl<I2C, INT, E, const ROWS: usize, const COLUMNS: usize> Pcf8574<I2C, INT, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + Read<Error = E>,
+    INT: InputPin,
+{
+    /// Use the input pin connected to the active-low interrupt output. While
+    /// the interrupt is armed and the pin is deasserted, [`scan()`](Pcf8574::scan)
+    /// returns the state found by the last scan without using the bus.
+    pub fn with_interrupt<P: InputPin>(self, interrupt: P) -> Pcf8574<I2C, P, ROWS, COLUMNS> {
+        Pcf8574 {
+            i2c: self.i2c,
+            address: self.address,
+            armed: self.armed,
+            interrupt,
+            state: self.state,
+            fresh: false,
+        }
+    }
+
+    /// The I2C bus.
+    pub fn i2c(&self) -> &I2C {
+        &self.i2c
+    }
+
+    /// Give back the I2C bus and the interrupt pin.
+    pub fn release(self) -> (I2C, INT) {
+        (self.i2c, self.interrupt)
+    }
+
+    /// Hold every column low between scans, so that pressing any key
+    /// asserts the interrupt output.
+    pub fn arm_interrupt(&mut self) -> Result<(), E> {
+        self.armed = true;
+        self.fresh = false;
+        self.idle()?;
+        // Reading the pins clears the interrupt caused by the columns
+        // changing.
+        self.read().map(|_| ())
+    }
+
+    /// Release every column between scans again.
+    pub fn disarm_interrupt(&mut self) -> Result<(), E> {
+        self.armed = false;
+        self.fresh = false;
+        self.idle()
+    }
+
+    /// Read the state of every key, driving each column low in turn.
+    ///
+    /// If the interrupt is armed and the interrupt pin is deasserted, the
+    /// state found by the last scan is returned instead, without using the
+    /// bus.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        if self.armed && self.fresh && !asserted(&self.interrupt) {
+            return Ok(self.state);
+        }
+        self.fresh = false;
+        let mut state = KeyState::new();
+        // Release the columns even if reading them failed.
+        let read = self.read_columns(&mut state);
+        let idle = self.idle();
+        read?;
+        idle?;
+        self.state = state;
+        if self.armed {
+            // Reading the pins clears the interrupt. With every column low,
+            // each row with a key held reads low, so if that doesn't match
+            // the scan, a key changed in between and the next scan mustn't
+            // trust the interrupt.
+            let levels = self.read()?;
+            self.fresh = (0..ROWS).all(|row| {
+                let held = (0..COLUMNS).any(|column| state.is_pressed(Position::new(row, column)));
+                held == (levels & 1 << row == 0)
+            });
+        }
+        Ok(state)
+    }
+
+    fn read_columns(&mut self, state: &mut KeyState<ROWS, COLUMNS>) -> Result<(), E> {
+        for column in 0..COLUMNS {
+            self.write(!(1 << (ROWS + column)))?;
+            let levels = self.read()?;
+            for row in 0..ROWS {
+                state.set(Position::new(row, column), levels & 1 << row == 0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Release every pin, except the columns if the interrupt is armed.
+    fn idle(&mut self) -> Result<(), E> {
+        let columns = ((1u16 << COLUMNS) - 1) << ROWS;
+        if self.armed {
+            self.write(!columns as u8)
+        } else {
+            self.write(0xFF)
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), E> {
+        self.i2c.write(self.address, &[byte])
+    }
+
+    fn read(&mut self) -> Result<u8, E> {
+        let mut buffer = [0];
+        self.i2c.read(self.address, &mut buffer)?;
+        Ok(buffer[0])
+    }
+}
+
+impl<I2C, INT, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Pcf8574<I2C, INT, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + Read<Error = E>,
+    INT: InputPin,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Pcf8574::scan(self)
+    }
+}
+
+const IODIRA: u8 = 0x00;
+const IODIRB: u8 = 0x01;
+const GPINTENA: u8 = 0x04;
+const DEFVALA: u8 = 0x06;
+const INTCONA: u8 = 0x08;
+const GPPUA: u8 = 0x0C;
+const GPIOA: u8 = 0x12;
+const OLATB: u8 = 0x15;
+
+/// A keypad whose rows are connected to pins GPA0, GPA1, ... of an MCP23017,
+/// and whose columns are connected to pins GPB0, GPB1, ...
+///
+/// The rows are inputs with the MCP23017's pull-ups enabled. The columns are
+/// inputs too, except for the one being scanned, which is an output set low.
+/// There can be at most 8 rows and 8 columns. The registers must be in the
+/// default `IOCON.BANK = 0` layout.
+///
+/// When the interrupt is armed, the INTA output is asserted for as long as
+/// any key is held. With it connected to an input pin, scans while it's
+/// deasserted return a state with every key released, without using the
+/// bus.
+///
+/// ```
+/// use keypad::expander::Mcp23017;
+/// use keypad::mock_hal::MockMcp23017;
+/// use keypad::Position;
+///
+/// let mut keypad: Mcp23017<_, _, 4, 3> = Mcp23017::new(MockMcp23017::new(0x21), 0x21).unwrap();
+///
+/// // Press the key at row 2, column 1, which connects GPA2 and GPB1.
+/// keypad.i2c().connect(2, 9);
+/// let state = keypad.scan().unwrap();
+/// assert_eq!(state.pressed_keys().collect::<Vec<_>>(), [Position::new(2, 1)]);
+///
+/// keypad.arm_interrupt().unwrap();
+/// assert!(keypad.i2c().interrupt());
+/// keypad.i2c().disconnect(2, 9);
+/// assert!(!keypad.i2c().interrupt());
+/// ```
+///
+/// ```
+/// use keypad::expander::Mcp23017;
+/// use keypad::mock_hal::MockMcp23017;
+///
+/// let expander = MockMcp23017::new(0x21);
+/// let mut keypad: Mcp23017<_, _, 4, 3> = Mcp23017::new(&expander, 0x21)
+///     .unwrap()
+///     .with_interrupt(expander.interrupt_pin());
+/// keypad.arm_interrupt().unwrap();
+///
+/// let transfers = expander.transfers();
+/// assert!(!keypad.scan().unwrap().any());
+/// assert_eq!(expander.transfers(), transfers);
+///
+/// expander.connect(2, 9);
+/// assert_eq!(keypad.scan().unwrap().count(), 1);
+/// assert!(expander.transfers() > transfers);
+/// ```
+///
+/// If a read fails, the column being scanned is released before the error
+/// is returned.
+///
+/// ```
+/// use keypad::expander::Mcp23017;
+/// use keypad::mock_hal::{MockError, MockMcp23017, Schedule};
+///
+/// const IODIRB: u8 = MockMcp23017::IODIRA + 1;
+///
+/// let expander = MockMcp23017::new(0x21);
+/// let mut keypad: Mcp23017<_, _, 4, 3> = Mcp23017::new(&expander, 0x21).unwrap();
+/// expander.fail_reads(Schedule::Nth(2));
+/// assert_eq!(keypad.scan(), Err(MockError::Injected));
+/// assert_eq!(expander.register(IODIRB), 0xFF);
+/// ```
+#[derive(Debug)]
+pub struct Mcp23017<I2C, INT, const ROWS: usize, const COLUMNS: usize> {
+    i2c: I2C,
+    address: u8,
+    armed: bool,
+    interrupt: INT,
+}
+
+impl<I2C, E, const ROWS: usize, const COLUMNS: usize> Mcp23017<I2C, NoInterrupt, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Create a new driver for the expander at this address, and configure
+    /// its pins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 8 rows or more than 8 columns.
+    pub fn new(i2c: I2C, address: u8) -> Result<Self, E> {
+        assert!(
+            ROWS <= 8 && COLUMNS <= 8,
+            "too many rows or columns for an MCP23017"
+        );
+        let mut keypad = Self {
+            i2c,
+            address,
+            armed: false,
+            interrupt: NoInterrupt,
+        };
+        keypad.write(&[IODIRA, 0xFF, 0xFF])?;
+        keypad.write(&[GPPUA, Self::ROW_MASK])?;
+        keypad.write(&[OLATB, 0x00])?;
+        Ok(keypad)
+    }
+}
+
+impl<I2C, INT, E, const ROWS: usize, const COLUMNS: usize> Mcp23017<I2C, INT, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+    INT: InputPin,
+{
+    /// Use the input pin connected to the active-low INTA output. While the
+    /// interrupt is armed and the pin is deasserted, no key is held, so
+    /// [`scan()`](Mcp23017::scan) returns a state with every key released
+    /// without using the bus.
+    pub fn with_interrupt<P: InputPin>(self, interrupt: P) -> Mcp23017<I2C, P, ROWS, COLUMNS> {
+        Mcp23017 {
+            i2c: self.i2c,
+            address: self.address,
+            armed: self.armed,
+            interrupt,
+        }
+    }
+
+    const ROW_MASK: u8 = ((1u16 << ROWS) - 1) as u8;
+    const COLUMN_MASK: u8 = ((1u16 << COLUMNS) - 1) as u8;
+
+    /// The I2C bus.
+    pub fn i2c(&self) -> &I2C {
+        &self.i2c
+    }
+
+    /// Give back the I2C bus and the interrupt pin.
+    pub fn release(self) -> (I2C, INT) {
+        (self.i2c, self.interrupt)
+    }
+
+    /// Hold every column low between scans, and assert the INTA output
+    /// while any row is low.
+    pub fn arm_interrupt(&mut self) -> Result<(), E> {
+        self.armed = true;
+        self.write(&[DEFVALA, 0xFF, 0xFF])?;
+        self.write(&[INTCONA, Self::ROW_MASK])?;
+        self.write(&[GPINTENA, Self::ROW_MASK])?;
+        self.idle()
+    }
+
+    /// Stop using the interrupt, and release every column between scans
+    /// again.
+    pub fn disarm_interrupt(&mut self) -> Result<(), E> {
+        self.armed = false;
+        self.write(&[GPINTENA, 0x00])?;
+        self.idle()
+    }
+
+    /// Read the state of every key, driving each column low in turn.
+    ///
+    /// If the interrupt is armed and the interrupt pin is deasserted, every
+    /// key is released, so that's returned without using the bus.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        let mut state = KeyState::new();
+        if self.armed && !asserted(&self.interrupt) {
+            return Ok(state);
+        }
+        // Release the columns even if reading them failed.
+        let read = self.read_columns(&mut state);
+        let idle = self.idle();
+        read?;
+        idle?;
+        Ok(state)
+    }
+
+    fn read_columns(&mut self, state: &mut KeyState<ROWS, COLUMNS>) -> Result<(), E> {
+        for column in 0..COLUMNS {
+            self.write(&[IODIRB, !(1 << column)])?;
+            let mut levels = [0];
+            self.i2c.write_read(self.address, &[GPIOA], &mut levels)?;
+            for row in 0..ROWS {
+                state.set(Position::new(row, column), levels[0] & 1 << row == 0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Release every column, unless the interrupt is armed.
+    fn idle(&mut self) -> Result<(), E> {
+        if self.armed {
+            self.write(&[IODIRB, !Self::COLUMN_MASK])
+        } else {
+            self.write(&[IODIRB, 0xFF])
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.i2c.write(self.address, bytes)
+    }
+}
+
+impl<I2C, INT, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Mcp23017<I2C, INT, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+    INT: InputPin,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Mcp23017::scan(self)
+    }
+}
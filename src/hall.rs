@@ -0,0 +1,555 @@
+//! Analog Hall-effect keys, read one at a time through an analog
+//! multiplexer.
+//!
+//! A Hall-effect key doesn't just open and close: its sensor's output
+//! voltage changes smoothly as the key travels down. The [`Hall`] keypad
+//! selects each key's sensor through the address pins of an analog
+//! multiplexer, like a CD74HC4067, reads it with an ADC, and turns the
+//! reading into a travel distance using that key's [`Calibration`]. Each key
+//! is then pressed or released according to its [`Actuation`] settings.
+//!
+//! With rapid trigger, a key is released as soon as it moves up by a set
+//! distance, and pressed again as soon as it moves down by a set distance,
+//! wherever it is in its travel, instead of waiting for it to cross a fixed
+//! point.
+//!
+//! Travel is measured in thousandths of the full travel, from 0 at rest to
+//! [`FULL_TRAVEL`] at the bottom. [`Hall`] implements [`Scan`], so its key
+//! states can be turned into the usual key events.
+//!
+//! ```
+//! use keypad::hall::{Actuation, Calibration, Hall, MuxSelect};
+//! use keypad::mock_hal::{AnalogPin, MockAnalogMux, Wiring};
+//! use keypad::{EventTracker, KeyEvent, Position};
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let mux = MockAnalogMux::new();
+//! // Every sensor reads 2000 at rest, and 3000 at the bottom.
+//! for key in 0..4 {
+//!     mux.set(key, 2000);
+//! }
+//! let calibration = [[Calibration::new(2000, 3000); 2]; 2];
+//! let actuation = Actuation::new(400).with_rapid_trigger(100, 100);
+//! let mut keypad = Hall::new(
+//!     mux.adc(),
+//!     AnalogPin::<0>,
+//!     MuxSelect::new(mux.address_pins(), WIRING.delay()),
+//!     calibration,
+//!     actuation,
+//! );
+//! let mut tracker = EventTracker::new();
+//!
+//! // Key 3, at row 1, column 1, goes 60% of the way down.
+//! mux.set(3, 2600);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(1, 1))));
+//! assert_eq!(keypad.travel(Position::new(1, 1)), 600);
+//!
+//! // It comes up by 15%, which is enough to release it, even though it's
+//! // still past the actuation point.
+//! mux.set(3, 2450);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Released(Position::new(1, 1))));
+//!
+//! // Going down by 10% again presses it again.
+//! mux.set(3, 2550);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(1, 1))));
+//! ```
+
+use crate::matrix::ColumnSelect;
+use crate::{KeyState, Position, Scan};
+use core::marker::PhantomData;
+use embedded_hal::adc::{Channel, OneShot};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::OutputPin;
+
+/// The travel of a key that's all the way down.
+pub const FULL_TRAVEL: u16 = 1000;
+
+/// The ADC readings of one key's sensor at the top and bottom of its
+/// travel.
+///
+/// The reading at the bottom can be higher or lower than the one at rest,
+/// depending on which way round the magnet is.
+///
+/// ```
+/// use keypad::hall::Calibration;
+///
+/// // The reading goes down as the key goes down.
+/// let calibration = Calibration::new(3000, 2000);
+/// assert_eq!(calibration.travel(3000), 0);
+/// assert_eq!(calibration.travel(2750), 250);
+/// assert_eq!(calibration.travel(2000), 1000);
+///
+/// // Readings past either end are clamped.
+/// assert_eq!(calibration.travel(3100), 0);
+/// assert_eq!(calibration.travel(1900), 1000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Calibration {
+    /// The reading when the key isn't pressed.
+    pub rest: u16,
+    /// The reading when the key is all the way down.
+    pub bottom: u16,
+}
+
+impl Calibration {
+    /// Create a new `Calibration`.
+    pub const fn new(rest: u16, bottom: u16) -> Self {
+        Self { rest, bottom }
+    }
+
+    /// The travel for this reading, from 0 to [`FULL_TRAVEL`].
+    pub fn travel(&self, reading: u16) -> u16 {
+        let span = i32::from(self.bottom) - i32::from(self.rest);
+        if span == 0 {
+            return 0;
+        }
+        let moved = i32::from(reading) - i32::from(self.rest);
+        let travel = moved * i32::from(FULL_TRAVEL) / span;
+        travel.max(0).min(i32::from(FULL_TRAVEL)) as u16
+    }
+}
+
+/// When a key counts as pressed, in thousandths of its travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Actuation {
+    /// The travel at which the key is pressed.
+    pub point: u16,
+    /// How far above the actuation point the key must come back up before
+    /// it's released, so that noise doesn't make it flicker.
+    pub hysteresis: u16,
+    /// The rapid trigger settings, if it's enabled.
+    pub rapid_trigger: Option<RapidTrigger>,
+}
+
+impl Actuation {
+    /// Press the key when its travel reaches this point, without hysteresis
+    /// or rapid trigger.
+    pub const fn new(point: u16) -> Self {
+        Self {
+            point,
+            hysteresis: 0,
+            rapid_trigger: None,
+        }
+    }
+
+    /// Release the key only once its travel is this far above the
+    /// actuation point.
+    pub const fn with_hysteresis(mut self, hysteresis: u16) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Enable rapid trigger. Past the actuation point, the key is pressed
+    /// again after moving down by `press`, and released after moving up by
+    /// `release`.
+    ///
+    /// A key that's released this way, without coming back above the
+    /// actuation point, is pressed again relative to the highest point it
+    /// reached since, not the actuation point.
+    ///
+    /// ```
+    /// use keypad::hall::{Actuation, Calibration, Hall, MuxSelect};
+    /// use keypad::mock_hal::{AnalogPin, MockAnalogMux, Wiring};
+    /// use keypad::Position;
+    ///
+    /// static WIRING: Wiring = Wiring::new();
+    ///
+    /// // Readings go from 0 at rest to 1000 at the bottom, so they're the
+    /// // travel.
+    /// let mux = MockAnalogMux::new();
+    /// let mut keypad: Hall<_, _, _, _, 1, 1> = Hall::new(
+    ///     mux.adc(),
+    ///     AnalogPin::<0>,
+    ///     MuxSelect::new(mux.address_pins(), WIRING.delay()),
+    ///     [[Calibration::new(0, 1000)]],
+    ///     Actuation::new(400).with_rapid_trigger(100, 50),
+    /// );
+    /// let mut pressed_at = |travel| {
+    ///     mux.set(0, travel);
+    ///     keypad.scan().unwrap().is_pressed(Position::new(0, 0))
+    /// };
+    ///
+    /// assert!(!pressed_at(350));
+    /// assert!(pressed_at(400));
+    /// assert!(pressed_at(700));
+    /// // Coming up by 50 from the deepest point releases it.
+    /// assert!(pressed_at(660));
+    /// assert!(!pressed_at(650));
+    /// // It turns at 500, so it's pressed again at 600, not at 750.
+    /// assert!(!pressed_at(500));
+    /// assert!(!pressed_at(590));
+    /// assert!(pressed_at(600));
+    /// // Once it comes back above the actuation point, rapid trigger starts
+    /// // over.
+    /// assert!(!pressed_at(390));
+    /// assert!(!pressed_at(399));
+    /// assert!(pressed_at(400));
+    /// ```
+    pub const fn with_rapid_trigger(mut self, press: u16, release: u16) -> Self {
+        self.rapid_trigger = Some(RapidTrigger { press, release });
+        self
+    }
+}
+
+/// Rapid trigger distances, in thousandths of a key's travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RapidTrigger {
+    /// How far down a released key must move to be pressed again.
+    pub press: u16,
+    /// How far up a pressed key must move to be released.
+    pub release: u16,
+}
+
+/// What a [`Hall`] keypad remembers about one key.
+#[derive(Debug, Clone, Copy, Default)]
+struct Key {
+    travel: u16,
+    pressed: bool,
+    /// The deepest travel since the key was pressed, or the shallowest since
+    /// it was released.
+    extreme: u16,
+}
+
+impl Key {
+    fn update(&mut self, travel: u16, actuation: &Actuation) {
+        self.travel = travel;
+        if travel.saturating_add(actuation.hysteresis) < actuation.point {
+            // The key came all the way back up.
+            self.pressed = false;
+            self.extreme = travel;
+        } else if self.pressed {
+            self.extreme = self.extreme.max(travel);
+            if let Some(rapid) = actuation.rapid_trigger {
+                if travel.saturating_add(rapid.release) <= self.extreme {
+                    self.pressed = false;
+                    self.extreme = travel;
+                }
+            }
+        } else {
+            self.extreme = self.extreme.min(travel);
+            // A key that was released by rapid trigger, without coming all
+            // the way back up, is pressed again relative to where it turned.
+            let threshold = match actuation.rapid_trigger {
+                Some(rapid)
+                    if self.extreme.saturating_add(actuation.hysteresis) >= actuation.point =>
+                {
+                    self.extreme.saturating_add(rapid.press)
+                }
+                _ => actuation.point,
+            };
+            if travel >= threshold {
+                self.pressed = true;
+                self.extreme = travel;
+            }
+        }
+    }
+}
+
+/// A keypad of `ROWS` rows and `COLUMNS` columns of Hall-effect keys, read
+/// through an analog multiplexer.
+///
+/// The key at row `r` and column `c` is multiplexer input
+/// `r * COLUMNS + c`. `S` chooses the input, usually with a [`MuxSelect`]
+/// connected to the multiplexer's address pins. `A` reads the multiplexer's
+/// output through the analog pin `P`, and the `ADC` type parameter is the
+/// ADC peripheral it converts with, as in `embedded_hal::adc::OneShot`. The
+/// selector and the ADC must have the same error type.
+#[derive(Debug)]
+pub struct Hall<ADC, A, P, S, const ROWS: usize, const COLUMNS: usize> {
+    adc: A,
+    pin: P,
+    select: S,
+    calibration: [[Calibration; COLUMNS]; ROWS],
+    actuation: [[Actuation; COLUMNS]; ROWS],
+    keys: [[Key; COLUMNS]; ROWS],
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC, A, P, S, E, const ROWS: usize, const COLUMNS: usize> Hall<ADC, A, P, S, ROWS, COLUMNS>
+where
+    A: OneShot<ADC, u16, P, Error = E>,
+    P: Channel<ADC>,
+    S: ColumnSelect<Error = E>,
+{
+    /// Create a new keypad, with the same actuation settings for every key.
+    pub fn new(
+        adc: A,
+        pin: P,
+        select: S,
+        calibration: [[Calibration; COLUMNS]; ROWS],
+        actuation: Actuation,
+    ) -> Self {
+        Self {
+            adc,
+            pin,
+            select,
+            calibration,
+            actuation: [[actuation; COLUMNS]; ROWS],
+            keys: [[Key::default(); COLUMNS]; ROWS],
+            _adc: PhantomData,
+        }
+    }
+
+    /// Give back the ADC, analog pin and multiplexer selector.
+    pub fn release(self) -> (A, P, S) {
+        (self.adc, self.pin, self.select)
+    }
+
+    /// The calibration of the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn calibration(&self, position: Position) -> Calibration {
+        self.calibration[position.row][position.column]
+    }
+
+    /// Change the calibration of the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn set_calibration(&mut self, position: Position, calibration: Calibration) {
+        self.calibration[position.row][position.column] = calibration;
+    }
+
+    /// Change the actuation settings of the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn set_actuation(&mut self, position: Position, actuation: Actuation) {
+        self.actuation[position.row][position.column] = actuation;
+    }
+
+    /// The travel of the key at this position in the last scan.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn travel(&self, position: Position) -> u16 {
+        self.keys[position.row][position.column].travel
+    }
+
+    /// Read every key while none of them are pressed, and use the readings
+    /// as their rest positions.
+    ///
+    /// The multiplexer is deselected even if a reading fails. If both fail,
+    /// the reading's error is returned.
+    ///
+    /// ```
+    /// use keypad::hall::{Actuation, Calibration, Hall, MuxSelect};
+    /// use keypad::mock_hal::{AnalogPin, MockAnalogMux, Wiring};
+    /// use keypad::Position;
+    ///
+    /// static WIRING: Wiring = Wiring::new();
+    ///
+    /// // The sensors' rest readings vary, but they all bottom out at 3000.
+    /// let mux = MockAnalogMux::new();
+    /// mux.set(0, 2000);
+    /// mux.set(1, 2200);
+    /// let calibration = [[Calibration::new(2000, 3000); 2]];
+    /// let mut keypad = Hall::new(
+    ///     mux.adc(),
+    ///     AnalogPin::<0>,
+    ///     MuxSelect::new(mux.address_pins(), WIRING.delay()),
+    ///     calibration,
+    ///     Actuation::new(400),
+    /// );
+    ///
+    /// // Before calibrating, the second key looks 20% of the way down.
+    /// keypad.scan().unwrap();
+    /// assert_eq!(keypad.travel(Position::new(0, 1)), 200);
+    ///
+    /// keypad.calibrate_rest().unwrap();
+    /// assert_eq!(keypad.calibration(Position::new(0, 1)), Calibration::new(2200, 3000));
+    /// keypad.scan().unwrap();
+    /// assert_eq!(keypad.travel(Position::new(0, 1)), 0);
+    ///
+    /// // Its travel is measured from the new rest position.
+    /// mux.set(1, 2600);
+    /// assert!(keypad.scan().unwrap().is_pressed(Position::new(0, 1)));
+    /// assert_eq!(keypad.travel(Position::new(0, 1)), 500);
+    /// ```
+    pub fn calibrate_rest(&mut self) -> Result<(), E> {
+        let out = self.read_rest();
+        let deselected = self.select.deselect();
+        out?;
+        deselected
+    }
+
+    fn read_rest(&mut self) -> Result<(), E> {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                self.calibration[row][column].rest = self.read(row, column)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read every key, and return which ones are pressed.
+    ///
+    /// The multiplexer is deselected even if a reading fails. If both fail,
+    /// the reading's error is returned.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        let mut state = KeyState::new();
+        let out = self.read_keys(&mut state);
+        let deselected = self.select.deselect();
+        out?;
+        deselected?;
+        Ok(state)
+    }
+
+    fn read_keys(&mut self, state: &mut KeyState<ROWS, COLUMNS>) -> Result<(), E> {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let reading = self.read(row, column)?;
+                let travel = self.calibration[row][column].travel(reading);
+                let key = &mut self.keys[row][column];
+                key.update(travel, &self.actuation[row][column]);
+                state.set(Position::new(row, column), key.pressed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Select one key's sensor, and read it.
+    fn read(&mut self, row: usize, column: usize) -> Result<u16, E> {
+        self.select.select(row * COLUMNS + column)?;
+        nb::block!(self.adc.read(&mut self.pin))
+    }
+}
+
+impl<ADC, A, P, S, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Hall<ADC, A, P, S, ROWS, COLUMNS>
+where
+    A: OneShot<ADC, u16, P, Error = E>,
+    P: Channel<ADC>,
+    S: ColumnSelect<Error = E>,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Hall::scan(self)
+    }
+}
+
+/// A [`ColumnSelect`] that chooses an input of an analog multiplexer, like a
+/// CD74HC4067, through its `BITS` address pins, and then waits for the
+/// multiplexer's output to settle before it's read.
+///
+/// Address pin `i` is connected to address input S`i`, so pin 0 is the least
+/// significant bit. The enable pin is optional, and is connected to the
+/// multiplexer's active-low enable input. Without it, deselecting does
+/// nothing, and the last input selected stays connected.
+///
+/// ```
+/// use keypad::hall::MuxSelect;
+/// use keypad::matrix::ColumnSelect;
+/// use keypad::mock_hal::{MockAnalogMux, Wiring};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let mux = MockAnalogMux::new();
+/// let mut select = MuxSelect::new(mux.address_pins(), WIRING.delay()).with_settle_us(20);
+/// select.select(11).unwrap();
+/// assert_eq!(mux.selected(), 11);
+/// assert_eq!(WIRING.clock().now_us(), 20);
+/// ```
+#[derive(Debug)]
+pub struct MuxSelect<A, N, T, const BITS: usize> {
+    address: [A; BITS],
+    enable: Option<N>,
+    delay: T,
+    settle_us: u8,
+}
+
+impl<A, T, E, const BITS: usize> MuxSelect<A, A, T, BITS>
+where
+    A: OutputPin<Error = E>,
+    T: DelayUs<u8>,
+{
+    /// Create a new selector for a multiplexer that's always enabled, which
+    /// waits 10 µs for it to settle.
+    pub fn new(address: [A; BITS], delay: T) -> Self {
+        Self {
+            address,
+            enable: None,
+            delay,
+            settle_us: 10,
+        }
+    }
+}
+
+impl<A, N, T, E, const BITS: usize> MuxSelect<A, N, T, BITS>
+where
+    A: OutputPin<Error = E>,
+    N: OutputPin<Error = E>,
+    T: DelayUs<u8>,
+{
+    /// Create a new selector for a multiplexer with an enable pin, which
+    /// waits 10 µs for it to settle.
+    pub fn with_enable(address: [A; BITS], enable: N, delay: T) -> Self {
+        Self {
+            address,
+            enable: Some(enable),
+            delay,
+            settle_us: 10,
+        }
+    }
+
+    /// Change how long to wait after selecting an input.
+    pub fn with_settle_us(mut self, settle_us: u8) -> Self {
+        self.settle_us = settle_us;
+        self
+    }
+
+    /// Give back the address pins, the enable pin if there is one, and the
+    /// delay.
+    pub fn release(self) -> ([A; BITS], Option<N>, T) {
+        (self.address, self.enable, self.delay)
+    }
+}
+
+impl<A, N, T, E, const BITS: usize> ColumnSelect for MuxSelect<A, N, T, BITS>
+where
+    A: OutputPin<Error = E>,
+    N: OutputPin<Error = E>,
+    T: DelayUs<u8>,
+{
+    type Error = E;
+
+    /// Select this input, and wait for the output to settle. If there's an
+    /// enable pin, the multiplexer is disabled while the address changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input is `2^BITS` or more.
+    fn select(&mut self, input: usize) -> Result<(), E> {
+        assert!(input >> BITS == 0, "input out of range");
+        self.deselect()?;
+        for (bit, pin) in self.address.iter_mut().enumerate() {
+            if input >> bit & 1 != 0 {
+                pin.set_high()?;
+            } else {
+                pin.set_low()?;
+            }
+        }
+        if let Some(enable) = &mut self.enable {
+            enable.set_low()?;
+        }
+        self.delay.delay_us(self.settle_us);
+        Ok(())
+    }
+
+    fn deselect(&mut self) -> Result<(), E> {
+        match &mut self.enable {
+            Some(enable) => enable.set_high(),
+            None => Ok(()),
+        }
+    }
+}
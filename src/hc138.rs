@@ -0,0 +1,145 @@
+//! Selecting keypad columns through a 74HC138 decoder.
+//!
+//! A 74HC138 3-to-8 line decoder sets one of its 8 outputs low, chosen by
+//! the binary number on its 3 address inputs. Connecting its outputs to the
+//! columns of a keypad matrix lets the [`Hc138`] column selector drive 8
+//! columns with only 3 pins, or 4 with a smaller 2-to-4 decoder like the
+//! 74HC139. Column `c` is output Y`c`.
+//!
+//! ```
+//! use keypad::hc138::Hc138;
+//! use keypad::matrix::{ColumnSelect, Matrix};
+//! use keypad::mock_hal::{GpioExt, Operation, Wiring, GPIOA};
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let pins = GPIOA::split_wired(&WIRING);
+//! let address = [
+//!     pins.pa0.into_push_pull_output().erase(),
+//!     pins.pa1.into_push_pull_output().erase(),
+//!     pins.pa2.into_push_pull_output().erase(),
+//! ];
+//! let ids = [address[0].id(), address[1].id(), address[2].id()];
+//! let enable = pins.pa3.into_push_pull_output().erase();
+//! let enable_id = enable.id();
+//! let mut columns = Hc138::with_enable(address, enable);
+//!
+//! let trace = WIRING.trace();
+//! trace.start();
+//!
+//! // Column 6 is 0b110.
+//! columns.select(6).unwrap();
+//! assert_eq!(trace.last_output(ids[0]), Some(Operation::SetLow));
+//! assert_eq!(trace.last_output(ids[1]), Some(Operation::SetHigh));
+//! assert_eq!(trace.last_output(ids[2]), Some(Operation::SetHigh));
+//! assert_eq!(trace.last_output(enable_id), Some(Operation::SetLow));
+//!
+//! // Deselecting disables the decoder, so every output is high.
+//! columns.deselect().unwrap();
+//! assert_eq!(trace.last_output(enable_id), Some(Operation::SetHigh));
+//!
+//! // It can scan a whole matrix, just like a chain of shift registers.
+//! let rows = [pins.pa4.into_pull_up_input().erase()];
+//! let mut keypad: Matrix<_, _, 1, 8> = Matrix::new(rows, columns);
+//! assert!(!keypad.scan().unwrap().any());
+//! ```
+
+use crate::matrix::ColumnSelect;
+use embedded_hal::digital::v2::OutputPin;
+
+/// A [`ColumnSelect`] that selects up to `2^BITS` columns through a decoder
+/// with `BITS` address inputs.
+///
+/// Address pin `i` is connected to address input A`i` of the decoder, so
+/// pin 0 is the least significant bit. The enable pin is optional, and is
+/// connected to one of the decoder's active-low enable inputs.
+///
+/// A selected column is low and the rest are high, like the columns of an
+/// ordinary keypad matrix with pull-up rows. The decoder's outputs are
+/// push-pull, so every column needs a diode with its cathode towards the
+/// decoder. Without them, pressing two keys in the same row connects the
+/// low output to a high one, shorting them.
+///
+/// Without an enable pin, an enabled decoder always has one output low, so
+/// deselecting does nothing, and the last column selected stays selected
+/// between scans. Setting a [`ColumnPin`](crate::matrix::ColumnPin) high
+/// doesn't release its column either, so keys in that column keep pulling
+/// their rows low. That's fine when the rows are only read while a column
+/// is selected, like in a [`Matrix`](crate::matrix::Matrix) or through
+/// [`KeypadInput`](crate::KeypadInput)s, but if anything else reads the row
+/// pins, or wakes up when one goes low, use an enable pin.
+#[derive(Debug)]
+pub struct Hc138<A, N, const BITS: usize> {
+    address: [A; BITS],
+    enable: Option<N>,
+}
+
+impl<A, E, const BITS: usize> Hc138<A, A, BITS>
+where
+    A: OutputPin<Error = E>,
+{
+    /// Create a new column selector for a decoder that's always enabled.
+    pub fn new(address: [A; BITS]) -> Self {
+        Self {
+            address,
+            enable: None,
+        }
+    }
+}
+
+impl<A, N, E, const BITS: usize> Hc138<A, N, BITS>
+where
+    A: OutputPin<Error = E>,
+    N: OutputPin<Error = E>,
+{
+    /// Create a new column selector for a decoder with an enable pin.
+    pub fn with_enable(address: [A; BITS], enable: N) -> Self {
+        Self {
+            address,
+            enable: Some(enable),
+        }
+    }
+
+    /// Give back the address pins, and the enable pin if there is one.
+    pub fn release(self) -> ([A; BITS], Option<N>) {
+        (self.address, self.enable)
+    }
+}
+
+impl<A, N, E, const BITS: usize> ColumnSelect for Hc138<A, N, BITS>
+where
+    A: OutputPin<Error = E>,
+    N: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    /// Select this column. If there's an enable pin, the decoder is disabled
+    /// while the address changes, so no other column is selected on the
+    /// way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is `2^BITS` or more.
+    fn select(&mut self, column: usize) -> Result<(), E> {
+        assert!(column >> BITS == 0, "column out of range");
+        self.deselect()?;
+        for (bit, pin) in self.address.iter_mut().enumerate() {
+            if column >> bit & 1 != 0 {
+                pin.set_high()?;
+            } else {
+                pin.set_low()?;
+            }
+        }
+        match &mut self.enable {
+            Some(enable) => enable.set_low(),
+            None => Ok(()),
+        }
+    }
+
+    fn deselect(&mut self) -> Result<(), E> {
+        match &mut self.enable {
+            Some(enable) => enable.set_high(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Reading keypad rows through 74HC165 shift registers.
+//!
+//! A chain of 74HC165 parallel-in, serial-out shift registers can read 8 rows
+//! per chip using only three pins, or an SPI bus and one pin. The [`Hc165`]
+//! row reader loads every row into the chain at the same moment, and then
+//! shifts all of them in, so a whole column is read in one go instead of one
+//! `InputPin` at a time.
+//!
+//! Row 0 is input A of the first chip in the chain, the one whose serial
+//! output QH is connected to the microcontroller. Row 7 is its input H, row 8
+//! is input A of the second chip, and so on. The bits are shifted in most
+//! significant bit first, with the first chip's byte first.
+//!
+//! ```
+//! use keypad::hc165::{Hc165, Spi as RowSpi};
+//! use keypad::hc595::{Hc595, Pattern, Spi as ColumnSpi};
+//! use keypad::matrix::Matrix;
+//! use keypad::mock_hal::{GpioExt, MockSpi, GPIOA};
+//! use keypad::Position;
+//!
+//! let pins = GPIOA::split();
+//! let columns: Hc595<_, 3> = Hc595::new(
+//!     ColumnSpi::new(MockSpi::new(), pins.pa0.into_push_pull_output()),
+//!     Pattern::OneCold,
+//! );
+//!
+//! // What the two chips read while each of the 3 columns is selected. A
+//! // pressed key pulls its row low.
+//! let readings = &[0xFF, 0xFF, 0b1111_1011, 0xFF, 0xFF, 0b1111_1110];
+//! let rows: Hc165<_, 10> = Hc165::new(RowSpi::new(
+//!     MockSpi::with_responses(readings),
+//!     pins.pa1.into_push_pull_output(),
+//! ));
+//!
+//! let mut keypad: Matrix<_, _, 10, 3> = Matrix::new(rows, columns);
+//! let state = keypad.scan().unwrap();
+//! let pressed: Vec<Position> = state.pressed_keys().collect();
+//! assert_eq!(pressed, [Position::new(2, 1), Position::new(8, 2)]);
+//! ```
+
+use crate::matrix::RowRead;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A way to load and shift bytes out of a chain of 74HC165s.
+pub trait ShiftIn {
+    /// The error that can occur while shifting.
+    type Error;
+
+    /// Copy the parallel inputs into the shift registers.
+    fn load(&mut self) -> Result<(), Self::Error>;
+
+    /// Shift out one byte, most significant bit first.
+    fn shift_byte(&mut self) -> Result<u8, Self::Error>;
+}
+
+/// Shift bits in by toggling two GPIO pins and reading a third.
+///
+/// The pins are connected to the first chip's load input (SH/LD), clock
+/// input (CLK) and serial output (QH). The load pin should be high and the
+/// clock pin low to begin with.
+#[derive(Debug)]
+pub struct BitBang<L, C, D> {
+    load: L,
+    clock: C,
+    data: D,
+}
+
+impl<L, C, D> BitBang<L, C, D> {
+    /// Shift bits in through these pins.
+    pub fn new(load: L, clock: C, data: D) -> Self {
+        Self { load, clock, data }
+    }
+
+    /// Give back the load, clock and data pins.
+    pub fn release(self) -> (L, C, D) {
+        (self.load, self.clock, self.data)
+    }
+}
+
+impl<L, C, D, E> ShiftIn for BitBang<L, C, D>
+where
+    L: OutputPin<Error = E>,
+    C: OutputPin<Error = E>,
+    D: InputPin<Error = E>,
+{
+    type Error = E;
+
+    fn load(&mut self) -> Result<(), E> {
+        self.load.set_low()?;
+        self.load.set_high()
+    }
+
+    fn shift_byte(&mut self) -> Result<u8, E> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = byte << 1 | u8::from(self.data.is_high()?);
+            self.clock.set_high()?;
+            self.clock.set_low()?;
+        }
+        Ok(byte)
+    }
+}
+
+/// Shift bytes in through an SPI bus, after loading them with a GPIO pin.
+///
+/// The bus must sample on the rising clock edge, most significant bit first,
+/// as in SPI mode 0. Its MISO and SCK lines are connected to the first chip's
+/// QH and CLK, and the load pin to SH/LD. The load pin should be high to
+/// begin with.
+#[derive(Debug)]
+pub struct Spi<S, L> {
+    spi: S,
+    load: L,
+}
+
+impl<S, L> Spi<S, L> {
+    /// Shift bytes in through this bus, after loading them with this pin.
+    pub fn new(spi: S, load: L) -> Self {
+        Self { spi, load }
+    }
+
+    /// The SPI bus.
+    pub fn spi(&self) -> &S {
+        &self.spi
+    }
+
+    /// Give back the SPI bus and the load pin.
+    pub fn release(self) -> (S, L) {
+        (self.spi, self.load)
+    }
+}
+
+impl<S, L, E> ShiftIn for Spi<S, L>
+where
+    S: spi::Transfer<u8, Error = E>,
+    L: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn load(&mut self) -> Result<(), E> {
+        self.load.set_low()?;
+        self.load.set_high()
+    }
+
+    fn shift_byte(&mut self) -> Result<u8, E> {
+        let mut words = [0];
+        Ok(self.spi.transfer(&mut words)?[0])
+    }
+}
+
+/// A [`RowRead`] that reads `ROWS` rows through a chain of 74HC165 shift
+/// registers.
+///
+/// By default, a row is active when it's low, like the rows of an ordinary
+/// keypad matrix with pull-up resistors.
+#[derive(Debug)]
+pub struct Hc165<T, const ROWS: usize> {
+    transport: T,
+    active_high: bool,
+}
+
+impl<T: ShiftIn, const ROWS: usize> Hc165<T, ROWS> {
+    /// Create a new row reader, where low rows are active.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            active_high: false,
+        }
+    }
+
+    /// Make high rows active instead, for rows with pull-down resistors.
+    pub fn active_high(mut self) -> Self {
+        self.active_high = true;
+        self
+    }
+
+    /// The transport used to shift in bits.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Give back the transport.
+    pub fn release(self) -> T {
+        self.transport
+    }
+}
+
+impl<T: ShiftIn, const ROWS: usize> RowRead<ROWS> for Hc165<T, ROWS> {
+    type Error = T::Error;
+
+    fn read_rows(&mut self) -> Result<[bool; ROWS], T::Error> {
+        self.transport.load()?;
+        let mut rows = [false; ROWS];
+        for chunk in rows.chunks_mut(8) {
+            let byte = self.transport.shift_byte()?;
+            for (bit, row) in chunk.iter_mut().enumerate() {
+                *row = (byte >> bit & 1 != 0) == self.active_high;
+            }
+        }
+        Ok(rows)
+    }
+}
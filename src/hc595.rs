@@ -0,0 +1,252 @@
+//! Driving keypad columns through 74HC595 shift registers.
+//!
+//! A chain of 74HC595 serial-in, parallel-out shift registers can drive 8
+//! columns per chip using only three pins, or an SPI bus and one pin. The
+//! [`Hc595`] column selector shifts a pattern with one active column into the
+//! chain, and then pulses the latch to update all the outputs at once.
+//!
+//! Column 0 is output QA of the first chip in the chain, the one whose serial
+//! input is connected to the microcontroller. Column 7 is its output QH,
+//! column 8 is output QA of the second chip, and so on. The bits are shifted
+//! in most significant bit first, with the last chip's byte first, so the
+//! byte for columns 0 to 7 is the last one shifted.
+//!
+//! ```
+//! use keypad::embedded_hal::digital::v2::OutputPin;
+//! use keypad::hc595::{BitBang, Hc595, Pattern};
+//! use keypad::matrix::ColumnSelect;
+//! use keypad::mock_hal::{GpioExt, Operation, Wiring, GPIOA};
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let pins = GPIOA::split_wired(&WIRING);
+//! let data = pins.pa0.into_push_pull_output();
+//! let data_id = data.id();
+//! let mut columns: Hc595<_, 8> = Hc595::new(
+//!     BitBang::new(data, pins.pa1.into_push_pull_output(), pins.pa2.into_push_pull_output()),
+//!     Pattern::OneCold,
+//! );
+//!
+//! WIRING.trace().start();
+//! columns.select(2).unwrap();
+//!
+//! // Column 2 is low and the rest are high, most significant bit first.
+//! let bits: Vec<bool> = WIRING
+//!     .trace()
+//!     .entries()
+//!     .filter(|entry| entry.pin == data_id)
+//!     .map(|entry| entry.operation == Operation::SetHigh)
+//!     .collect();
+//! assert_eq!(bits, [true, true, true, true, true, false, true, true]);
+//! ```
+
+use crate::matrix::ColumnSelect;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::{OutputPin, PinState};
+
+/// A way to shift bytes into a chain of 74HC595s.
+pub trait ShiftOut {
+    /// The error that can occur while shifting.
+    type Error;
+
+    /// Shift in one byte, most significant bit first.
+    fn shift_byte(&mut self, byte: u8) -> Result<(), Self::Error>;
+
+    /// Copy the shifted bits to the outputs.
+    fn latch(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Shift bits out by toggling three GPIO pins.
+///
+/// The pins are connected to the first chip's serial data input (SER), shift
+/// clock (SRCLK) and latch clock (RCLK). The clock and latch pins should be
+/// low to begin with.
+#[derive(Debug)]
+pub struct BitBang<D, C, L> {
+    data: D,
+    clock: C,
+    latch: L,
+}
+
+impl<D, C, L> BitBang<D, C, L> {
+    /// Shift bits out through these pins.
+    pub fn new(data: D, clock: C, latch: L) -> Self {
+        Self { data, clock, latch }
+    }
+
+    /// Give back the data, clock and latch pins.
+    pub fn release(self) -> (D, C, L) {
+        (self.data, self.clock, self.latch)
+    }
+}
+
+impl<D, C, L, E> ShiftOut for BitBang<D, C, L>
+where
+    D: OutputPin<Error = E>,
+    C: OutputPin<Error = E>,
+    L: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn shift_byte(&mut self, byte: u8) -> Result<(), E> {
+        for bit in (0..8).rev() {
+            self.data.set_state(PinState::from(byte >> bit & 1 != 0))?;
+            self.clock.set_high()?;
+            self.clock.set_low()?;
+        }
+        Ok(())
+    }
+
+    fn latch(&mut self) -> Result<(), E> {
+        self.latch.set_high()?;
+        self.latch.set_low()
+    }
+}
+
+/// Shift bytes out through an SPI bus, and latch them with a GPIO pin.
+///
+/// The bus must be in SPI mode 0, sending the most significant bit first. Its
+/// MOSI and SCK lines are connected to the first chip's SER and SRCLK, and the
+/// latch pin to RCLK. The latch pin should be low to begin with.
+#[derive(Debug)]
+pub struct Spi<S, L> {
+    spi: S,
+    latch: L,
+}
+
+impl<S, L> Spi<S, L> {
+    /// Shift bytes out through this bus, and latch them with this pin.
+    pub fn new(spi: S, latch: L) -> Self {
+        Self { spi, latch }
+    }
+
+    /// The SPI bus.
+    pub fn spi(&self) -> &S {
+        &self.spi
+    }
+
+    /// Give back the SPI bus and the latch pin.
+    pub fn release(self) -> (S, L) {
+        (self.spi, self.latch)
+    }
+}
+
+impl<S, L, E> ShiftOut for Spi<S, L>
+where
+    S: spi::Write<u8, Error = E>,
+    L: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn shift_byte(&mut self, byte: u8) -> Result<(), E> {
+        self.spi.write(&[byte])
+    }
+
+    fn latch(&mut self) -> Result<(), E> {
+        self.latch.set_high()?;
+        self.latch.set_low()
+    }
+}
+
+/// The level of the selected column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// The selected column is high, and the rest are low.
+    OneHot,
+    /// The selected column is low, and the rest are high, like the columns of
+    /// an ordinary keypad matrix with pull-up rows.
+    OneCold,
+}
+
+/// A [`ColumnSelect`] that drives `COLUMNS` columns through a chain of
+/// 74HC595 shift registers.
+///
+/// Any outputs past the last column are kept inactive.
+///
+/// The 74HC595's outputs are push-pull, so every column needs a diode,
+/// pointing from the rows towards the chip with [`Pattern::OneCold`], or
+/// from the chip towards the rows with [`Pattern::OneHot`]. Without them,
+/// pressing two keys in the same row connects the selected output to an
+/// inactive one, shorting them.
+///
+/// Deselecting shifts out a pattern with no active column, so it works
+/// without the output enable pin. Until the first column is selected or
+/// deselected, though, the outputs hold whatever the chips powered up
+/// with, so call [`deselect()`](ColumnSelect::deselect) before reading the
+/// rows any other way, or hold the output enable pin high until then.
+///
+/// ```
+/// use keypad::hc595::{Hc595, Pattern, Spi};
+/// use keypad::matrix::ColumnSelect;
+/// use keypad::mock_hal::{GpioExt, MockSpi, GPIOA};
+///
+/// let pins = GPIOA::split();
+/// let mut columns: Hc595<_, 12> = Hc595::new(
+///     Spi::new(MockSpi::new(), pins.pa0.into_push_pull_output()),
+///     Pattern::OneHot,
+/// );
+///
+/// // Two chips, with the second chip's byte first.
+/// columns.select(9).unwrap();
+/// columns.deselect().unwrap();
+/// assert_eq!(columns.transport().spi().written(), [0b0000_0010, 0, 0, 0]);
+/// ```
+#[derive(Debug)]
+pub struct Hc595<T, const COLUMNS: usize> {
+    transport: T,
+    pattern: Pattern,
+}
+
+impl<T: ShiftOut, const COLUMNS: usize> Hc595<T, COLUMNS> {
+    /// Create a new column selector. The outputs aren't changed until a
+    /// column is selected or deselected.
+    pub fn new(transport: T, pattern: Pattern) -> Self {
+        Self { transport, pattern }
+    }
+
+    /// The transport used to shift out bits.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Give back the transport.
+    pub fn release(self) -> T {
+        self.transport
+    }
+
+    /// Shift out a pattern with this column active, or none, and latch it.
+    fn write(&mut self, selected: Option<usize>) -> Result<(), T::Error> {
+        let inactive = match self.pattern {
+            Pattern::OneHot => 0x00,
+            Pattern::OneCold => 0xFF,
+        };
+        for chip in (0..(COLUMNS + 7) / 8).rev() {
+            let mut byte = inactive;
+            if let Some(column) = selected {
+                if column / 8 == chip {
+                    byte ^= 1 << (column % 8);
+                }
+            }
+            self.transport.shift_byte(byte)?;
+        }
+        self.transport.latch()
+    }
+}
+
+impl<T: ShiftOut, const COLUMNS: usize> ColumnSelect for Hc595<T, COLUMNS> {
+    type Error = T::Error;
+
+    /// Select this column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is `COLUMNS` or more.
+    fn select(&mut self, column: usize) -> Result<(), T::Error> {
+        assert!(column < COLUMNS, "column out of range");
+        self.write(Some(column))
+    }
+
+    fn deselect(&mut self) -> Result<(), T::Error> {
+        self.write(None)
+    }
+}
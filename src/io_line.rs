@@ -0,0 +1,70 @@
+//! Pins that are switched between input and output modes while scanning.
+//!
+//! This is shared by the backends that drive and read the same pins, like
+//! [`Charlieplex`](crate::charlieplex::Charlieplex) and
+//! [`Duplex`](crate::duplex::Duplex).
+
+use embedded_hal::digital::v2::{InputPin, IoPin, OutputPin, PinState};
+
+/// An error from a keypad whose pins are switched between input and output
+/// modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineError<E> {
+    /// Reading a pin or switching its mode failed.
+    Pin(E),
+    /// Switching a pin's mode failed earlier, and the HAL didn't give the pin
+    /// back, so it can't be used any more.
+    Lost,
+}
+
+impl<E> From<E> for LineError<E> {
+    fn from(error: E) -> Self {
+        LineError::Pin(error)
+    }
+}
+
+/// One pin, in whichever mode it's currently in.
+///
+/// Every pin is an input, except while it's being driven. If switching it
+/// between modes fails, the HAL doesn't give the pin back, so it's lost, and
+/// any later attempt to use it returns [`LineError::Lost`].
+pub(crate) enum Line<I, O> {
+    Input(I),
+    Output(O),
+    /// Switching the pin failed, so it's gone.
+    Lost,
+}
+
+impl<I, O, E> Line<I, O>
+where
+    I: InputPin<Error = E> + IoPin<I, O, Error = E>,
+    O: OutputPin<Error = E> + IoPin<I, O, Error = E>,
+{
+    /// Read a pin that's currently an input.
+    pub(crate) fn read_low(&self) -> Result<bool, LineError<E>> {
+        match self {
+            Line::Input(pin) => Ok(pin.is_low()?),
+            _ => Err(LineError::Lost),
+        }
+    }
+
+    /// Switch a pin from an input to an output, and set it low.
+    pub(crate) fn drive_low(&mut self) -> Result<(), LineError<E>> {
+        // Every pin is an input between reads, so only a lost pin can be in
+        // the wrong mode here.
+        match core::mem::replace(self, Line::Lost) {
+            Line::Input(pin) => *self = Line::Output(pin.into_output_pin(PinState::Low)?),
+            _ => return Err(LineError::Lost),
+        }
+        Ok(())
+    }
+
+    /// Switch a pin from an output back to an input.
+    pub(crate) fn release(&mut self) -> Result<(), LineError<E>> {
+        match core::mem::replace(self, Line::Lost) {
+            Line::Output(pin) => *self = Line::Input(pin.into_input_pin()?),
+            _ => return Err(LineError::Lost),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! Keypads that connect every key to one analog pin through a resistor
+//! ladder.
+//!
+//! Pressing a key of a resistor ladder keypad connects a different voltage
+//! divider to the pin, so each key produces its own ADC reading. A
+//! calibration table of [`Window`]s says which range of readings belongs to
+//! which key. Only one key can be read at a time: with several pressed, the
+//! reading usually matches just one of them.
+//!
+//! [`Ladder`] implements [`Scan`], so a ladder keypad can be used with an
+//! [`EventTracker`](crate::EventTracker) like a matrix keypad.
+//!
+//! ```
+//! use keypad::ladder::{Ladder, Window};
+//! use keypad::mock_hal::{AnalogPin, MockAdc};
+//! use keypad::{EventTracker, KeyEvent, Position};
+//!
+//! const WINDOWS: [Window; 3] = [
+//!     Window::new(Position::new(0, 0), 0, 200),
+//!     Window::new(Position::new(0, 1), 400, 700),
+//!     Window::new(Position::new(1, 0), 900, 1300),
+//! ];
+//!
+//! let mut keypad: Ladder<_, _, _, 2, 2> =
+//!     Ladder::new(MockAdc::new(), AnalogPin::<0>, &WINDOWS).with_hysteresis(50);
+//! let mut tracker = EventTracker::new();
+//!
+//! // Nothing is pressed, so the pin is pulled all the way up.
+//! keypad.adc().set(0, 4095);
+//! assert_eq!(tracker.update(keypad.scan().unwrap()).next(), None);
+//!
+//! keypad.adc().set(0, 520);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(0, 1))));
+//!
+//! // Noise just outside the window doesn't release the key.
+//! keypad.adc().set(0, 730);
+//! assert_eq!(tracker.update(keypad.scan().unwrap()).next(), None);
+//! ```
+
+use crate::{KeyState, Position, Scan};
+use core::marker::PhantomData;
+use embedded_hal::adc::{Channel, OneShot};
+
+/// The range of ADC readings produced by one key of a resistor ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Window {
+    /// The position of the key.
+    pub position: Position,
+    /// The lowest reading of the key, inclusive.
+    pub min: u16,
+    /// The highest reading of the key, inclusive.
+    pub max: u16,
+}
+
+impl Window {
+    /// Create a new `Window`.
+    pub const fn new(position: Position, min: u16, max: u16) -> Self {
+        Self { position, min, max }
+    }
+
+    /// Is this reading inside the window, after widening it by `margin` on
+    /// both sides?
+    fn contains(&self, reading: u16, margin: u16) -> bool {
+        self.min.saturating_sub(margin) <= reading && reading <= self.max.saturating_add(margin)
+    }
+}
+
+/// A resistor ladder keypad on one analog pin, whose keys are at positions
+/// in a grid of `ROWS` rows and `COLUMNS` columns.
+///
+/// Readings outside of every window mean that no key is pressed. Once a key
+/// is found, it stays pressed until the reading leaves its window widened by
+/// the hysteresis, so noise near the edge of a window doesn't make it
+/// flicker.
+///
+/// The `ADC` type parameter is the ADC peripheral that `A` converts with, as
+/// in `embedded_hal::adc::OneShot`.
+#[derive(Debug)]
+pub struct Ladder<'w, ADC, A, P, const ROWS: usize, const COLUMNS: usize> {
+    adc: A,
+    pin: P,
+    windows: &'w [Window],
+    hysteresis: u16,
+    current: Option<usize>,
+    _adc: PhantomData<ADC>,
+}
+
+impl<'w, ADC, A, P, const ROWS: usize, const COLUMNS: usize> Ladder<'w, ADC, A, P, ROWS, COLUMNS>
+where
+    A: OneShot<ADC, u16, P>,
+    P: Channel<ADC>,
+{
+    /// Create a new resistor ladder keypad, with no hysteresis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a window's position is outside of the keypad.
+    pub fn new(adc: A, pin: P, windows: &'w [Window]) -> Self {
+        for window in windows {
+            assert!(
+                window.position.row < ROWS && window.position.column < COLUMNS,
+                "resistor ladder window outside of the keypad"
+            );
+        }
+        Self {
+            adc,
+            pin,
+            windows,
+            hysteresis: 0,
+            current: None,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Widen the window of the pressed key by this much on both sides.
+    pub fn with_hysteresis(mut self, hysteresis: u16) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// The ADC.
+    pub fn adc(&self) -> &A {
+        &self.adc
+    }
+
+    /// Give back the ADC and the analog pin.
+    pub fn release(self) -> (A, P) {
+        (self.adc, self.pin)
+    }
+
+    /// Read the ADC, waiting for the conversion to finish, and return the
+    /// position of the pressed key, if there is one.
+    pub fn read_key(&mut self) -> Result<Option<Position>, A::Error> {
+        let reading = nb::block!(self.adc.read(&mut self.pin))?;
+        let hysteresis = self.hysteresis;
+        let held = self
+            .current
+            .filter(|&index| self.windows[index].contains(reading, hysteresis));
+        self.current = held.or_else(|| {
+            self.windows
+                .iter()
+                .position(|window| window.contains(reading, 0))
+        });
+        Ok(self.current.map(|index| self.windows[index].position))
+    }
+
+    /// Read the state of every key. At most one is pressed.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, A::Error> {
+        let mut state = KeyState::new();
+        if let Some(position) = self.read_key()? {
+            state.set(position, true);
+        }
+        Ok(state)
+    }
+}
+
+impl<'w, ADC, A, P, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Ladder<'w, ADC, A, P, ROWS, COLUMNS>
+where
+    A: OneShot<ADC, u16, P>,
+    P: Channel<ADC>,
+{
+    type Error = A::Error;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, A::Error> {
+        Ladder::scan(self)
+    }
+}
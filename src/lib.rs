@@ -38,8 +38,8 @@
 //! - Reading the key state is not reentrant.
 //!
 //! - This is not optimized for scanning through the entire keypad as quickly as
-//! possible. That's a tradeoff that comes from treating each key
-//! as an independent input.
+//!   possible. That's a tradeoff that comes from treating each key
+//!   as an independent input.
 //!
 //!
 //! ## Example
@@ -128,6 +128,9 @@
 //!
 #![no_std]
 #![warn(missing_docs)]
+// Newer compilers check `cfg` names, and don't know about the docs.rs
+// workaround below. Older ones don't know about that lint.
+#![allow(unknown_lints, unexpected_cfgs)]
 // Workaround needed as long as docs.rs is using rustc <1.30
 #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
 
@@ -140,7 +143,30 @@ pub extern crate embedded_hal;
 #[doc(hidden)]
 pub extern crate core as _core;
 
+pub mod braille;
+pub mod charlieplex;
+pub mod dtmf;
+pub mod duplex;
+pub mod expander;
+pub mod hall;
+pub mod hc138;
+pub mod hc165;
+pub mod hc595;
+mod io_line;
+pub mod ladder;
+pub mod matrix;
+pub mod midi;
 pub mod mock_hal;
+pub mod morse;
+pub mod numeric;
+pub mod polarity;
+pub mod scan;
+pub mod tca8418;
+pub mod ttp229;
+pub mod velocity;
+
+pub use polarity::{Polarity, PolarityMask};
+pub use scan::{EventTracker, Events, KeyEvent, KeyState, Position, Scan};
 
 use core::cell::RefCell;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
@@ -157,15 +183,19 @@ use embedded_hal::digital::v2::{InputPin, OutputPin};
 /// This has several implications.
 ///
 /// 1) Reading from `KeypadInput`s is not reentrant. If we were in the middle
-/// of reading a `KeypadInput` and entered an interrupt service routine that
-/// read any `KeypadInput` of the same keypad, we might read an incorrect value
-/// or cause a `panic`.
+///    of reading a `KeypadInput` and entered an interrupt service routine that
+///    read any `KeypadInput` of the same keypad, we might read an incorrect value
+///    or cause a `panic`.
 ///
 /// 2) Reading from a `KeypadInput` is slower than reading from a real input
-/// pin, because it needs to change the output pin state twice for every read.
+///    pin, because it needs to change the output pin state twice for every read.
+///
+/// If the key is [`Polarity::NormallyClosed`], the reading is inverted, so the
+/// pin reads low while the key is pressed either way.
 pub struct KeypadInput<'a, E> {
     row: &'a dyn InputPin<Error = E>,
     col: &'a RefCell<dyn OutputPin<Error = E>>,
+    polarity: Polarity,
 }
 
 impl<'a, E> KeypadInput<'a, E> {
@@ -174,7 +204,17 @@ impl<'a, E> KeypadInput<'a, E> {
         row: &'a dyn InputPin<Error = E>,
         col: &'a RefCell<dyn OutputPin<Error = E>>,
     ) -> Self {
-        Self { row, col }
+        Self {
+            row,
+            col,
+            polarity: Polarity::NormallyOpen,
+        }
+    }
+
+    /// Set the polarity of the key, and return the pin. For use in macros.
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
     }
 }
 
@@ -186,11 +226,17 @@ impl<'a, E> InputPin for KeypadInput<'a, E> {
     }
 
     /// Read the state of the key at this row and column. Not reentrant.
+    ///
+    /// The column pin is set high again even if reading the row pin fails, so
+    /// an error never leaves the column selected. If both fail, the row pin's
+    /// error is returned.
     fn is_low(&self) -> Result<bool, E> {
         self.col.borrow_mut().set_low()?;
-        let out = self.row.is_low()?;
-        self.col.borrow_mut().set_high()?;
-        Ok(out)
+        let out = self.row.is_low();
+        let restored = self.col.borrow_mut().set_high();
+        let out = out?;
+        restored?;
+        Ok(self.polarity.is_pressed(out))
     }
 }
 
@@ -211,6 +257,134 @@ impl<'a, E> InputPin for KeypadInput<'a, E> {
 /// the methods implemented by this macro, documented here:
 /// [`example_generated::ExampleKeypad`](./example_generated/struct.ExampleKeypad.html)
 ///
+/// The struct also implements the [`Scan`](./scan/trait.Scan.html) trait, so
+/// it can be used anywhere a whole-keypad [`KeyState`](./scan/struct.KeyState.html)
+/// is needed.
+///
+/// # Direct keys
+///
+/// Keys that are wired straight from their own input pin to ground, outside
+/// of the matrix, can be declared in an optional `direct: (...)` section after
+/// the columns. They're read with `decompose_direct()`, and `scan()` puts them
+/// in one extra row after the matrix rows: with R rows, direct key `i` is at
+/// `Position::new(R, i)`. The scanned state has as many columns as the matrix
+/// or the direct keys, whichever is more, and `release()` gives back the
+/// direct pins as a third tuple.
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use core::convert::Infallible;
+/// use keypad::embedded_hal::digital::v2::OutputPin;
+/// use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+/// use keypad::{EventTracker, KeyEvent, Position};
+///
+/// keypad_struct! {
+///     pub struct PowerKeypad<Error = Infallible> {
+///         rows: (
+///             mock_hal::gpioa::PA0<Input<PullUp>>,
+///             mock_hal::gpioa::PA1<Input<PullUp>>,
+///         ),
+///         columns: (
+///             mock_hal::gpioa::PA2<Output<OpenDrain>>,
+///             mock_hal::gpioa::PA3<Output<OpenDrain>>,
+///         ),
+///         direct: (
+///             mock_hal::gpioa::PA4<Input<PullUp>>,
+///         ),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// # fn main() {
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let power_id = pins.pa4.id();
+/// // Pressing the power key connects its pin to a grounded pin.
+/// let mut ground = pins.pa5.into_push_pull_output();
+/// ground.set_low().unwrap();
+/// let ground_id = ground.id();
+///
+/// let keypad = keypad_new!(PowerKeypad {
+///     rows: (pins.pa0.into_pull_up_input(), pins.pa1.into_pull_up_input()),
+///     columns: (pins.pa2.into_open_drain_output(), pins.pa3.into_open_drain_output()),
+///     direct: (pins.pa4.into_pull_up_input(),),
+/// });
+/// let mut tracker = EventTracker::new();
+///
+/// WIRING.connect(power_id, ground_id);
+/// let mut events = tracker.update(keypad.scan().unwrap());
+/// assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(2, 0))));
+///
+/// let (_rows, _columns, (_power,)) = keypad.release();
+/// # }
+/// ```
+///
+/// # Polarity
+///
+/// Every key is assumed to be normally open. A key with a normally-closed
+/// switch can be given [`Polarity::NormallyClosed`] with `with_polarity()` or
+/// `set_polarity()`, and `scan()` and the virtual pins from `decompose()` will
+/// invert its reading, so they say whether the key is really pressed. The
+/// pins from `decompose_direct()` are the real pins, so they aren't inverted.
+/// Other backends can be wrapped in a
+/// [`Polarized`](crate::polarity::Polarized) to do the same.
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use core::convert::Infallible;
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+/// use keypad::{EventTracker, KeyEvent, Polarity, Position};
+///
+/// keypad_struct! {
+///     pub struct SafetyKeypad<Error = Infallible> {
+///         rows: (mock_hal::gpioa::PA0<Input<PullUp>>,),
+///         columns: (mock_hal::gpioa::PA1<Output<OpenDrain>>,),
+///         direct: (mock_hal::gpioa::PA2<Input<PullUp>>,),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// # fn main() {
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let guard_id = pins.pa2.id();
+/// let mut ground = pins.pa3.into_push_pull_output();
+/// ground.set_low().unwrap();
+/// let ground_id = ground.id();
+///
+/// // The guard switch is closed until it's pressed.
+/// let guard = Position::new(1, 0);
+/// WIRING.connect(guard_id, ground_id);
+/// let keypad = keypad_new!(SafetyKeypad {
+///     rows: (pins.pa0.into_pull_up_input(),),
+///     columns: (pins.pa1.into_open_drain_output(),),
+///     direct: (pins.pa2.into_pull_up_input(),),
+/// })
+/// .with_polarity(guard, Polarity::NormallyClosed)
+/// .with_polarity(Position::new(0, 0), Polarity::NormallyClosed);
+/// let mut tracker = EventTracker::new();
+///
+/// // The matrix key's switch is open, so it's pressed, and its virtual pin
+/// // says so too.
+/// let matrix_key = keypad.scan().unwrap();
+/// assert_eq!(matrix_key.pressed_keys().collect::<Vec<_>>(), [Position::new(0, 0)]);
+/// assert!(keypad.decompose()[0][0].is_low().unwrap());
+///
+/// tracker.update(matrix_key);
+///
+/// WIRING.disconnect(guard_id, ground_id);
+/// let mut events = tracker.update(keypad.scan().unwrap());
+/// assert_eq!(events.next(), Some(KeyEvent::Pressed(guard)));
+/// # }
+/// ```
+///
 /// # Example
 ///
 /// ```
@@ -249,7 +423,7 @@ impl<'a, E> InputPin for KeypadInput<'a, E> {
 /// This macro uses `unsafe` to create an array with uninitialized memory, which
 /// is then immediately initialized in a loop. This is fine as long as there is
 /// not a bug in how the macro calculates the dimensions of the array.
-
+//
 // There are two reasons why this big, scary macro is necessary:
 //
 // 1) Every single pin has a unique type, and we don't know which pins will be used. We know that
@@ -306,6 +480,7 @@ macro_rules! keypad_struct {
         $(#[$attributes:meta])* $visibility:vis struct $struct_name:ident {
             rows: ( $($row_type:ty),* $(,)* ),
             columns: ( $($col_type:ty),* $(,)* ),
+            $( direct: ( $($direct_type:ty),* $(,)* ), )?
         }
     ) => {
         compile_error!("You must specify the associated `Error` type of the row and column pins'\
@@ -317,6 +492,21 @@ macro_rules! keypad_struct {
             rows: ( $($row_type:ty),* $(,)* ),
             columns: ( $($col_type:ty),* $(,)* ),
         }
+    ) => {
+        keypad_struct! {
+            $(#[$attributes])* $visibility struct $struct_name <Error = $error_type> {
+                rows: ($($row_type),*),
+                columns: ($($col_type),*),
+                direct: (),
+            }
+        }
+    };
+    (
+        $(#[$attributes:meta])* $visibility:vis struct $struct_name:ident <Error = $error_type:ty> {
+            rows: ( $($row_type:ty),* $(,)* ),
+            columns: ( $($col_type:ty),* $(,)* ),
+            direct: ( $($direct_type:ty),* $(,)* ),
+        }
     ) => {
         $(#[$attributes])* $visibility struct $struct_name {
             /// The input pins used for reading each row.
@@ -327,6 +517,14 @@ macro_rules! keypad_struct {
             /// actively scan the matrix when reading the state of a virtual
             /// `KeypadInput` pin.
             columns: ($($crate::_core::cell::RefCell<$col_type>),* ,),
+            /// The input pins of keys that are wired directly to their own pin,
+            /// outside of the matrix.
+            direct: ($($direct_type,)*),
+            /// The polarity of every key, including the direct keys.
+            polarity: $crate::PolarityMask<
+                { keypad_struct!(@key_rows ($($row_type),*) ($($direct_type),*)) },
+                { keypad_struct!(@key_columns ($($col_type),*) ($($direct_type),*)) },
+            >,
         }
 
         impl $struct_name {
@@ -367,26 +565,166 @@ macro_rules! keypad_struct {
                 // Initialize each element with a KeypadInput struct
                 for r in 0..rows.len() {
                     for c in 0..columns.len() {
-                        out[r][c].write($crate::KeypadInput::new(rows[r], columns[c]));
+                        let polarity = self.polarity.polarity($crate::Position::new(r, c));
+                        out[r][c].write(
+                            $crate::KeypadInput::new(rows[r], columns[c]).with_polarity(polarity),
+                        );
                     }
                 }
                 // All elements are initialized. Transmute the array to the initialized type.
                 unsafe { $crate::_core::mem::transmute::<_, _>(out) }
             }
 
-            /// Give back ownership of the row and column pins.
+            /// Get an array of the input pins of the keys that are wired
+            /// directly to their own pin, in the order they were declared.
+            #[allow(dead_code)]
+            $visibility fn decompose_direct(&self) -> [
+                &dyn $crate::embedded_hal::digital::v2::InputPin<Error = $error_type>;
+                keypad_struct!(@count $($direct_type)*)
+            ] {
+                let direct: [
+                    &dyn $crate::embedded_hal::digital::v2::InputPin<Error = $error_type>;
+                    keypad_struct!(@count $($direct_type)*)
+                ]
+                    = keypad_struct!(@tuple  self.direct,  ($($direct_type),*));
+                direct
+            }
+
+            /// Set the polarity of the key at this position, and return the
+            /// keypad.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the position is outside of the scanned key state.
+            #[allow(dead_code)]
+            $visibility fn with_polarity(
+                mut self,
+                position: $crate::Position,
+                polarity: $crate::Polarity,
+            ) -> Self {
+                self.set_polarity(position, polarity);
+                self
+            }
+
+            /// Set the polarity of the key at this position.
             ///
-            /// This consumes the keypad struct. All references to its virtual
-            /// `KeypadInput` pins must have gone out of scope before you try to
-            /// call `.release()`, or it will fail to compile.
+            /// # Panics
             ///
-            /// The column pins will be returned inside of `RefCell`s (because
-            /// macros are hard). You can use `.into_inner()` to extract
-            /// each column pin from its `RefCell`.
+            /// Panics if the position is outside of the scanned key state.
+            #[allow(dead_code)]
+            $visibility fn set_polarity(
+                &mut self,
+                position: $crate::Position,
+                polarity: $crate::Polarity,
+            ) {
+                self.polarity.set(position, polarity);
+            }
+
+            /// The polarity of the key at this position.
             #[allow(dead_code)]
-            $visibility fn release(self) ->(($($row_type),* ,), ($($crate::_core::cell::RefCell<$col_type>),* ,)) {
-                (self.rows, self.columns)
+            $visibility fn polarity(&self, position: $crate::Position) -> $crate::Polarity {
+                self.polarity.polarity(position)
             }
+
+            /// Read the state of every key in the keypad matrix, one at a time.
+            ///
+            /// Keys that are wired directly to their own pin are in one extra
+            /// row after the matrix, in the order they were declared. The
+            /// readings of normally-closed keys are inverted, so the state
+            /// always says which keys are pressed.
+            #[allow(dead_code)]
+            $visibility fn scan(&self) -> Result<
+                $crate::KeyState<
+                    { keypad_struct!(@key_rows ($($row_type),*) ($($direct_type),*)) },
+                    { keypad_struct!(@key_columns ($($col_type),*) ($($direct_type),*)) },
+                >,
+                $error_type,
+            > {
+                let mut state = $crate::KeyState::new();
+                for (r, row) in self.decompose().iter().enumerate() {
+                    for (c, key) in row.iter().enumerate() {
+                        let pressed = $crate::embedded_hal::digital::v2::InputPin::is_low(key)?;
+                        state.set($crate::Position::new(r, c), pressed);
+                    }
+                }
+                for (c, key) in self.decompose_direct().iter().enumerate() {
+                    let position = $crate::Position::new(keypad_struct!(@count $($row_type)*), c);
+                    let low = $crate::embedded_hal::digital::v2::InputPin::is_low(*key)?;
+                    state.set(position, self.polarity.is_pressed(position, low));
+                }
+                Ok(state)
+            }
+
+            keypad_struct!(
+                @release $visibility,
+                ($($row_type),*),
+                ($($col_type),*),
+                ($($direct_type),*)
+            );
+        }
+
+        impl $crate::Scan<
+            { keypad_struct!(@key_rows ($($row_type),*) ($($direct_type),*)) },
+            { keypad_struct!(@key_columns ($($col_type),*) ($($direct_type),*)) },
+        > for $struct_name {
+            type Error = $error_type;
+
+            fn scan(&mut self) -> Result<
+                $crate::KeyState<
+                    { keypad_struct!(@key_rows ($($row_type),*) ($($direct_type),*)) },
+                    { keypad_struct!(@key_columns ($($col_type),*) ($($direct_type),*)) },
+                >,
+                $error_type,
+            > {
+                $struct_name::scan(self)
+            }
+        }
+    };
+    (@release $visibility:vis, ($($row:ty),*), ($($col:ty),*), ()) => {
+        /// Give back ownership of the row and column pins.
+        ///
+        /// This consumes the keypad struct. All references to its virtual
+        /// `KeypadInput` pins must have gone out of scope before you try to
+        /// call `.release()`, or it will fail to compile.
+        ///
+        /// The column pins will be returned inside of `RefCell`s (because
+        /// macros are hard). You can use `.into_inner()` to extract
+        /// each column pin from its `RefCell`.
+        #[allow(dead_code)]
+        $visibility fn release(self) ->(($($row),* ,), ($($crate::_core::cell::RefCell<$col>),* ,)) {
+            (self.rows, self.columns)
+        }
+    };
+    (@release $visibility:vis, ($($row:ty),*), ($($col:ty),*), ($($direct:ty),+)) => {
+        /// Give back ownership of the row, column and direct key pins.
+        ///
+        /// This consumes the keypad struct. All references to its virtual
+        /// `KeypadInput` pins must have gone out of scope before you try to
+        /// call `.release()`, or it will fail to compile.
+        ///
+        /// The column pins will be returned inside of `RefCell`s (because
+        /// macros are hard). You can use `.into_inner()` to extract
+        /// each column pin from its `RefCell`.
+        #[allow(dead_code)]
+        $visibility fn release(self) -> (
+            ($($row),* ,),
+            ($($crate::_core::cell::RefCell<$col>),* ,),
+            ($($direct),+ ,),
+        ) {
+            (self.rows, self.columns, self.direct)
+        }
+    };
+    (@key_rows ($($row:ty),*) ()) => {
+        keypad_struct!(@count $($row)*)
+    };
+    (@key_rows ($($row:ty),*) ($($direct:ty),+)) => {
+        keypad_struct!(@count $($row)*) + 1usize
+    };
+    (@key_columns ($($col:ty),*) ($($direct:ty),*)) => {
+        if keypad_struct!(@count $($col)*) > keypad_struct!(@count $($direct)*) {
+            keypad_struct!(@count $($col)*)
+        } else {
+            keypad_struct!(@count $($direct)*)
         }
     };
     (@array2d_type $element_type:ty, ($($row:ty),*) ($($col:ty),*) ) => {
@@ -427,6 +765,9 @@ macro_rules! keypad_struct {
             )
         )
     };
+    (@tuple $tuple:expr, ()) => {
+        []
+    };
     (@tuple $tuple:expr, ($($repeats:ty),*)) => {
         keypad_struct!(@tuple_helper $tuple, ($($repeats),*) , ())
     };
@@ -489,6 +830,20 @@ macro_rules! keypad_new {
         $struct_name {
             rows:  ($($row_val),* ,),
             columns:  ($($crate::_core::cell::RefCell::new($col_val)),* ,),
+            direct: (),
+            polarity: $crate::PolarityMask::new(),
+        }
+    };
+    ( $struct_name:ident {
+        rows: ( $($row_val:expr),* $(,)* ),
+        columns: ( $($col_val:expr),* $(,)* ),
+        direct: ( $($direct_val:expr),* $(,)* ),
+    }) => {
+        $struct_name {
+            rows:  ($($row_val),* ,),
+            columns:  ($($crate::_core::cell::RefCell::new($col_val)),* ,),
+            direct: ($($direct_val,)*),
+            polarity: $crate::PolarityMask::new(),
         }
     };
 }
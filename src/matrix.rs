@@ -0,0 +1,242 @@
+//! Keypad matrices whose rows or columns aren't ordinary GPIO pins.
+//!
+//! Anything that can select one column at a time, like a chain of shift
+//! registers, can implement [`ColumnSelect`], and anything that can read
+//! every row at once can implement [`RowRead`]. A [`Matrix`] combines the two
+//! to scan the whole keypad. [`ColumnPin`]s make each column look like an
+//! `OutputPin`, so it can be used with [`KeypadInput`](crate::KeypadInput) to
+//! read single keys.
+
+use crate::{KeyState, Position, Scan};
+use core::cell::RefCell;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Something that can select one column of a keypad matrix at a time.
+///
+/// A selected column is active, like a column `OutputPin` that's set low in
+/// an ordinary keypad matrix. At most one column is selected at a time.
+pub trait ColumnSelect {
+    /// The error that can occur while selecting a column.
+    type Error;
+
+    /// Select this column, and deselect all the others.
+    fn select(&mut self, column: usize) -> Result<(), Self::Error>;
+
+    /// Deselect every column.
+    fn deselect(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Something that can read every row of a keypad matrix at once.
+///
+/// It's implemented for arrays of `InputPin`s, where a row is active if its
+/// pin is low.
+pub trait RowRead<const ROWS: usize> {
+    /// The error that can occur while reading the rows.
+    type Error;
+
+    /// Read every row, with `true` for the rows that are active.
+    fn read_rows(&mut self) -> Result<[bool; ROWS], Self::Error>;
+}
+
+impl<I: InputPin, const ROWS: usize> RowRead<ROWS> for [I; ROWS] {
+    type Error = I::Error;
+
+    fn read_rows(&mut self) -> Result<[bool; ROWS], I::Error> {
+        let mut rows = [false; ROWS];
+        for (row, pin) in rows.iter_mut().zip(self.iter()) {
+            *row = pin.is_low()?;
+        }
+        Ok(rows)
+    }
+}
+
+/// A virtual `embedded-hal` output pin representing one column of a
+/// [`ColumnSelect`].
+///
+/// Setting it low selects its column, and setting it high deselects every
+/// column. Put these in `RefCell`s to use them as the columns of
+/// [`KeypadInput`](crate::KeypadInput)s.
+///
+/// ```
+/// use core::cell::RefCell;
+/// use keypad::embedded_hal::digital::v2::OutputPin;
+/// use keypad::matrix::{column_pins, ColumnSelect};
+///
+/// #[derive(Default)]
+/// struct Columns {
+///     selected: Option<usize>,
+/// }
+///
+/// impl ColumnSelect for Columns {
+///     type Error = ();
+///     fn select(&mut self, column: usize) -> Result<(), ()> {
+///         self.selected = Some(column);
+///         Ok(())
+///     }
+///     fn deselect(&mut self) -> Result<(), ()> {
+///         self.selected = None;
+///         Ok(())
+///     }
+/// }
+///
+/// let columns = RefCell::new(Columns::default());
+/// let mut pins = column_pins::<_, 3>(&columns);
+/// pins[2].set_low().unwrap();
+/// assert_eq!(columns.borrow().selected, Some(2));
+/// pins[2].set_high().unwrap();
+/// assert_eq!(columns.borrow().selected, None);
+/// ```
+pub struct ColumnPin<'a, S> {
+    select: &'a RefCell<S>,
+    column: usize,
+}
+
+impl<'a, S> ColumnPin<'a, S> {
+    /// Create a virtual pin for one column.
+    pub fn new(select: &'a RefCell<S>, column: usize) -> Self {
+        Self { select, column }
+    }
+}
+
+impl<'a, S: ColumnSelect> OutputPin for ColumnPin<'a, S> {
+    type Error = S::Error;
+
+    /// Deselect every column.
+    fn set_high(&mut self) -> Result<(), S::Error> {
+        self.select.borrow_mut().deselect()
+    }
+
+    /// Select this pin's column.
+    fn set_low(&mut self) -> Result<(), S::Error> {
+        self.select.borrow_mut().select(self.column)
+    }
+}
+
+/// Create a virtual pin for each of the first `COLUMNS` columns.
+pub fn column_pins<S, const COLUMNS: usize>(select: &RefCell<S>) -> [ColumnPin<'_, S>; COLUMNS] {
+    let mut column = 0;
+    [(); COLUMNS].map(|_| {
+        column += 1;
+        ColumnPin::new(select, column - 1)
+    })
+}
+
+/// A keypad matrix with rows read by a [`RowRead`], like an array of input
+/// pins, and columns selected by a [`ColumnSelect`]. It implements
+/// [`Scan`](crate::Scan).
+///
+/// The rows and the column selector must have the same error type.
+///
+/// ```
+/// use keypad::hc595::{Hc595, Pattern, Spi};
+/// use keypad::matrix::Matrix;
+/// use keypad::mock_hal::{GpioExt, MockSpi, GPIOA};
+///
+/// let pins = GPIOA::split();
+/// let columns: Hc595<_, 3> = Hc595::new(
+///     Spi::new(MockSpi::new(), pins.pa2.into_push_pull_output()),
+///     Pattern::OneCold,
+/// );
+/// let rows = [
+///     pins.pa0.into_pull_up_input().erase(),
+///     pins.pa1.into_pull_up_input().erase(),
+/// ];
+/// let mut keypad: Matrix<_, _, 2, 3> = Matrix::new(rows, columns);
+///
+/// assert!(!keypad.scan().unwrap().any());
+///
+/// // Each column was selected, and then deselected.
+/// let spi = keypad.columns().transport().spi();
+/// assert_eq!(spi.written(), [0xFE, 0xFF, 0xFD, 0xFF, 0xFB, 0xFF]);
+/// ```
+pub struct Matrix<R, S, const ROWS: usize, const COLUMNS: usize> {
+    rows: R,
+    columns: S,
+}
+
+impl<R, S, E, const ROWS: usize, const COLUMNS: usize> Matrix<R, S, ROWS, COLUMNS>
+where
+    R: RowRead<ROWS, Error = E>,
+    S: ColumnSelect<Error = E>,
+{
+    /// Create a new keypad matrix.
+    pub fn new(rows: R, columns: S) -> Self {
+        Self { rows, columns }
+    }
+
+    /// The row reader.
+    pub fn rows(&self) -> &R {
+        &self.rows
+    }
+
+    /// The column selector.
+    pub fn columns(&self) -> &S {
+        &self.columns
+    }
+
+    /// Give back the row reader and the column selector.
+    pub fn release(self) -> (R, S) {
+        (self.rows, self.columns)
+    }
+
+    /// Read the state of every key, selecting each column in turn.
+    ///
+    /// Like [`KeypadInput`](crate::KeypadInput), the columns are deselected
+    /// even if reading a row fails. If both fail, the row's error is
+    /// returned.
+    ///
+    /// ```
+    /// use keypad::hc138::Hc138;
+    /// use keypad::matrix::Matrix;
+    /// use keypad::mock_hal::{Faulty, GpioExt, MockError, Operation, Schedule, Wiring, GPIOA};
+    ///
+    /// static WIRING: Wiring = Wiring::new();
+    ///
+    /// let pins = GPIOA::split_wired(&WIRING);
+    /// let working = |pin| Faulty::new(pin, MockError::Injected, Schedule::Never);
+    /// let address = [
+    ///     working(pins.pa0.into_push_pull_output().erase()),
+    ///     working(pins.pa1.into_push_pull_output().erase()),
+    /// ];
+    /// let enable = pins.pa2.into_push_pull_output().erase();
+    /// let enable_id = enable.id();
+    /// let columns = Hc138::with_enable(address, working(enable));
+    /// // The row fails the second time it's read, while column 1 is selected.
+    /// let row = pins.pa3.into_pull_up_input().erase();
+    /// let rows = [Faulty::new(row, MockError::Injected, Schedule::Nth(2))];
+    /// let mut keypad: Matrix<_, _, 1, 4> = Matrix::new(rows, columns);
+    ///
+    /// WIRING.trace().start();
+    /// assert_eq!(keypad.scan(), Err(MockError::Injected));
+    ///
+    /// // The decoder was disabled anyway, so no column is left selected.
+    /// assert_eq!(WIRING.trace().last_output(enable_id), Some(Operation::SetHigh));
+    /// ```
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        let mut state = KeyState::new();
+        for column in 0..COLUMNS {
+            self.columns.select(column)?;
+            let out = self.rows.read_rows();
+            let restored = self.columns.deselect();
+            let out = out?;
+            restored?;
+            for (row, &active) in out.iter().enumerate() {
+                state.set(Position::new(row, column), active);
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl<R, S, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Matrix<R, S, ROWS, COLUMNS>
+where
+    R: RowRead<ROWS, Error = E>,
+    S: ColumnSelect<Error = E>,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Matrix::scan(self)
+    }
+}
@@ -0,0 +1,307 @@
+//! Playing a keypad like a MIDI instrument.
+//!
+//! [`MidiKeypad`] maps each key of the keypad to a [`MidiAction`], and turns
+//! key events into MIDI 1.0 messages: note on and note off for keys that play
+//! notes, and control change for keys that act as switches. The messages are
+//! plain bytes, so they can be written to a UART at 31250 baud or wrapped in
+//! USB-MIDI packets.
+//!
+//! ```
+//! use keypad::midi::MidiKeypad;
+//! use keypad::{KeyEvent, Position};
+//!
+//! // A 4x8 keypad playing chromatic notes on channel 0, starting at middle C.
+//! let mut midi = MidiKeypad::<4, 8>::chromatic(0, 60, 100);
+//!
+//! let message = midi.encode(KeyEvent::Pressed(Position::new(1, 2))).unwrap();
+//! assert_eq!(message.as_bytes(), &[0x90, 70, 100]);
+//!
+//! let message = midi.encode(KeyEvent::Released(Position::new(1, 2))).unwrap();
+//! assert_eq!(message.as_bytes(), &[0x80, 70, 64]);
+//! ```
+//!
+//! With running status enabled, the status byte is left out of a message when
+//! it's the same as the previous message's.
+//!
+//! ```
+//! # use keypad::midi::MidiKeypad;
+//! # use keypad::{KeyEvent, Position};
+//! let mut midi = MidiKeypad::<4, 8>::chromatic(0, 60, 100).with_running_status(true);
+//!
+//! let first = midi.encode(KeyEvent::Pressed(Position::new(0, 0))).unwrap();
+//! let second = midi.encode(KeyEvent::Pressed(Position::new(0, 4))).unwrap();
+//! assert_eq!(first.as_bytes(), &[0x90, 60, 100]);
+//! assert_eq!(second.as_bytes(), &[64, 100]);
+//! ```
+
+use crate::velocity::VelocityEvent;
+use crate::{KeyEvent, Position};
+
+/// The note off velocity used when a key doesn't have its own, as recommended
+/// by the MIDI specification.
+pub const DEFAULT_RELEASE_VELOCITY: u8 = 64;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const CONTROL_CHANGE: u8 = 0xB0;
+
+/// What a key does when it's pressed and released.
+///
+/// Channels are numbered 0 to 15, and all other values 0 to 127. Larger values
+/// are truncated to fit, except for note velocities, which must be 1 to 127,
+/// because a note on with velocity 0 means note off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiAction {
+    /// Send note on when pressed and note off when released.
+    Note {
+        /// The MIDI channel.
+        channel: u8,
+        /// The note number, where 60 is middle C.
+        note: u8,
+        /// The note on velocity.
+        velocity: u8,
+    },
+    /// Send a control change when pressed, and another one when released.
+    Control {
+        /// The MIDI channel.
+        channel: u8,
+        /// The controller number.
+        controller: u8,
+        /// The value sent when the key is pressed.
+        pressed_value: u8,
+        /// The value sent when the key is released.
+        released_value: u8,
+    },
+}
+
+/// One encoded MIDI message, up to 3 bytes long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MidiMessage {
+    bytes: [u8; 3],
+    len: usize,
+}
+
+impl MidiMessage {
+    /// The bytes of the message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Does this message start with a status byte? This is only false when
+    /// running status left it out.
+    pub fn has_status(&self) -> bool {
+        self.len > 0 && self.bytes[0] & 0x80 != 0
+    }
+}
+
+/// Turns key events from a keypad with `ROWS` rows and `COLUMNS` columns into
+/// MIDI messages.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug, Clone)]
+pub struct MidiKeypad<const ROWS: usize, const COLUMNS: usize> {
+    actions: [[Option<MidiAction>; COLUMNS]; ROWS],
+    running_status: bool,
+    /// The status byte of the last message, for running status.
+    last_status: Option<u8>,
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> MidiKeypad<ROWS, COLUMNS> {
+    /// Create a new `MidiKeypad` with an action for each key. Keys without an
+    /// action don't send any messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a note's velocity is 0 or more than 127.
+    pub fn new(actions: [[Option<MidiAction>; COLUMNS]; ROWS]) -> Self {
+        for action in actions.iter().flatten() {
+            check_action(action);
+        }
+        Self {
+            actions,
+            running_status: false,
+            last_status: None,
+        }
+    }
+
+    /// Create a new `MidiKeypad` where every key plays a note, one semitone
+    /// higher than the key before it, going left to right and then top to
+    /// bottom. Keys that would go above note 127 are left without an action.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the velocity is 0 or more than 127.
+    ///
+    /// ```should_panic
+    /// use keypad::midi::MidiKeypad;
+    ///
+    /// // Every press would be a note off.
+    /// let _ = MidiKeypad::<1, 4>::chromatic(0, 60, 0);
+    /// ```
+    pub fn chromatic(channel: u8, first_note: u8, velocity: u8) -> Self {
+        let mut actions = [[None; COLUMNS]; ROWS];
+        for (r, row) in actions.iter_mut().enumerate() {
+            for (c, action) in row.iter_mut().enumerate() {
+                let note = usize::from(first_note) + r * COLUMNS + c;
+                if note <= 127 {
+                    *action = Some(MidiAction::Note {
+                        channel,
+                        note: note as u8,
+                        velocity,
+                    });
+                }
+            }
+        }
+        Self::new(actions)
+    }
+
+    /// Choose whether to use running status, which leaves out the status byte
+    /// when it's the same as the previous message's. It's disabled by
+    /// default.
+    pub fn with_running_status(mut self, enabled: bool) -> Self {
+        self.running_status = enabled;
+        self.last_status = None;
+        self
+    }
+
+    /// The action for the key at this position, if it has one.
+    pub fn action(&self, position: Position) -> Option<MidiAction> {
+        self.actions
+            .get(position.row)
+            .and_then(|row| row.get(position.column))
+            .copied()
+            .flatten()
+    }
+
+    /// Change the action for the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad, or if a note's
+    /// velocity is 0 or more than 127.
+    pub fn set_action(&mut self, position: Position, action: Option<MidiAction>) {
+        check_action(&action);
+        self.actions[position.row][position.column] = action;
+    }
+
+    /// Make the next message include its status byte, even with running
+    /// status enabled. Call this if the receiver may have missed earlier
+    /// messages, or if something else was sent on the same transport.
+    pub fn reset_running_status(&mut self) {
+        self.last_status = None;
+    }
+
+    /// Encode the message for a key event, or return `None` if the key doesn't
+    /// have an action.
+    pub fn encode(&mut self, event: KeyEvent) -> Option<MidiMessage> {
+        self.encode_with_velocity(event, None)
+    }
+
+    /// Encode the message for a key event from a
+    /// [`VelocityTracker`](crate::velocity::VelocityTracker), using its
+    /// velocity instead of the key's own. Control change messages are the same
+    /// as with [`encode()`](MidiKeypad::encode).
+    ///
+    /// Note on velocities are kept between 1 and 127, because a note on with
+    /// velocity 0 means note off. Note off velocities are kept at 127 or less.
+    ///
+    /// ```
+    /// use keypad::midi::MidiKeypad;
+    /// use keypad::velocity::VelocityEvent;
+    /// use keypad::{KeyEvent, Position};
+    ///
+    /// let mut midi = MidiKeypad::<1, 4>::chromatic(0, 60, 100);
+    /// let message = midi.encode_velocity(VelocityEvent {
+    ///     event: KeyEvent::Pressed(Position::new(0, 2)),
+    ///     velocity: 37,
+    /// });
+    /// assert_eq!(message.unwrap().as_bytes(), &[0x90, 62, 37]);
+    ///
+    /// let press = |velocity| VelocityEvent {
+    ///     event: KeyEvent::Pressed(Position::new(0, 0)),
+    ///     velocity,
+    /// };
+    /// let release = |velocity| VelocityEvent {
+    ///     event: KeyEvent::Released(Position::new(0, 0)),
+    ///     velocity,
+    /// };
+    /// assert_eq!(midi.encode_velocity(press(0)).unwrap().as_bytes(), &[0x90, 60, 1]);
+    /// assert_eq!(midi.encode_velocity(press(128)).unwrap().as_bytes(), &[0x90, 60, 127]);
+    /// assert_eq!(midi.encode_velocity(release(0)).unwrap().as_bytes(), &[0x80, 60, 0]);
+    /// assert_eq!(midi.encode_velocity(release(200)).unwrap().as_bytes(), &[0x80, 60, 127]);
+    /// ```
+    pub fn encode_velocity(&mut self, event: VelocityEvent) -> Option<MidiMessage> {
+        let velocity = if event.event.is_press() {
+            event.velocity.clamp(1, 127)
+        } else {
+            event.velocity.min(127)
+        };
+        self.encode_with_velocity(event.event, Some(velocity))
+    }
+
+    fn encode_with_velocity(
+        &mut self,
+        event: KeyEvent,
+        velocity: Option<u8>,
+    ) -> Option<MidiMessage> {
+        let action = self.action(event.position())?;
+        let (status, data) = match (action, event.is_press()) {
+            (
+                MidiAction::Note {
+                    channel,
+                    note,
+                    velocity: own,
+                },
+                true,
+            ) => (NOTE_ON | channel & 0x0F, [note, velocity.unwrap_or(own)]),
+            (MidiAction::Note { channel, note, .. }, false) => (
+                NOTE_OFF | channel & 0x0F,
+                [note, velocity.unwrap_or(DEFAULT_RELEASE_VELOCITY)],
+            ),
+            (
+                MidiAction::Control {
+                    channel,
+                    controller,
+                    pressed_value,
+                    released_value,
+                },
+                is_press,
+            ) => {
+                let value = if is_press {
+                    pressed_value
+                } else {
+                    released_value
+                };
+                (CONTROL_CHANGE | channel & 0x0F, [controller, value])
+            }
+        };
+        Some(self.message(status, data))
+    }
+
+    fn message(&mut self, status: u8, data: [u8; 2]) -> MidiMessage {
+        let data = [data[0] & 0x7F, data[1] & 0x7F];
+        if self.running_status && self.last_status == Some(status) {
+            return MidiMessage {
+                bytes: [data[0], data[1], 0],
+                len: 2,
+            };
+        }
+        if self.running_status {
+            self.last_status = Some(status);
+        }
+        MidiMessage {
+            bytes: [status, data[0], data[1]],
+            len: 3,
+        }
+    }
+}
+
+/// Make sure a note's velocity won't be sent as note off, or truncated.
+fn check_action(action: &Option<MidiAction>) {
+    if let Some(MidiAction::Note { velocity, .. }) = action {
+        assert!(
+            (1..=127).contains(velocity),
+            "note velocity must be 1 to 127"
+        );
+    }
+}
@@ -3,13 +3,50 @@
 //!
 //! They're used for writing example code that will run on non-embedded targets.
 //!
+//! Pins created with `GpioExt::split()` are not connected to anything, so
+//! reading a key through them will always find it unpressed. Pins created
+//! with `GpioExt::split_wired()` are connected through a simulated [`Wiring`]
+//! instead, which lets tests press and release keys.
+//!
+//! Only port `GPIOA` is defined here. More ports can be declared with the
+//! [`mock_port!`](crate::mock_port) macro.
+//!
 //! Based on the [stm32f103xx_hal](https://github.com/japaric/stm32f103xx-hal)
 //! implementation by Jorge Aparicio.
 
+mod adc;
+mod clock;
+mod fault;
+mod i2c;
+mod noise;
+mod pin;
+mod spi;
+mod touch;
+mod trace;
+mod wiring;
+
+pub use self::adc::{
+    AnalogPin, MockAdc, MockAnalogMux, MuxAdc, MuxAddressPin, ADC_CHANNELS, MUX_INPUTS,
+};
+pub use self::clock::{MockClock, MockDelay};
+pub use self::fault::{Faulty, FloatPolicy, MockError, PinError, Schedule};
+pub use self::i2c::{InterruptPin, MockMcp23017, MockPcf8574, MockTca8418};
+pub use self::noise::Noise;
+#[doc(hidden)]
+pub use self::pin::PinCore;
+pub use self::pin::{InputMode, Mode, OutputMode};
+pub use self::spi::{MockSpi, SPI_CAPACITY};
+pub use self::touch::{MockTtp229, Ttp229Clock, Ttp229Data};
+pub use self::trace::{Operation, Trace, TraceEntry, TRACE_CAPACITY};
+#[doc(hidden)]
+pub use self::wiring::Drive;
+pub use self::wiring::{MockMatrix, PinId, Wiring, MAX_PORTS, PINS_PER_PORT};
+
+use core::convert::Infallible;
 use core::marker::PhantomData;
 
-/// The internal state of a mock input or output pin.
-#[derive(Debug)]
+/// The level of a mock pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum State {
     High,
     Low,
@@ -30,6 +67,10 @@ pub struct Floating;
 #[derive(Debug)]
 pub struct PullUp;
 
+/// Pulled down input marker
+#[derive(Debug)]
+pub struct PullDown;
+
 /// Output mode marker
 #[derive(Debug)]
 pub struct Output<MODE> {
@@ -51,17 +92,308 @@ pub trait GpioExt {
 
     /// Split the GPIO block into independent pins and registers
     fn split() -> Self::Parts;
+
+    /// Split the GPIO block into pins that are connected to each other
+    /// through the given wiring.
+    fn split_wired(wiring: &'static Wiring) -> Self::Parts;
+}
+
+/// A mock GPIO pin whose port and pin number are only known at runtime.
+///
+/// Real HALs have "erased" or "degraded" pins like this, so that pins from
+/// different ports can be stored in the same array. Any mock pin can be
+/// converted into one with its `erase()` method. It keeps the error type of
+/// the port it came from.
+///
+/// ```
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::{ErasedPin, GpioExt, Input, PinId, PullUp, Wiring, GPIOA};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let rows: [ErasedPin<Input<PullUp>>; 2] = [
+///     pins.pa0.into_pull_up_input().erase(),
+///     pins.pa5.into_pull_up_input().erase(),
+/// ];
+/// let mut column = pins.pa1.into_open_drain_output().erase();
+/// assert_eq!(rows[1].id(), PinId::new(0, 5));
+///
+/// column.set_low().unwrap();
+/// WIRING.connect(rows[1].id(), column.id());
+/// assert!(rows[0].is_high().unwrap());
+/// assert!(rows[1].is_low().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct ErasedPin<MODE, E = Infallible> {
+    core: PinCore,
+    _mode: PhantomData<(MODE, E)>,
+}
+
+impl<MODE, E> ErasedPin<MODE, E> {
+    #[doc(hidden)]
+    pub fn from_core(core: PinCore) -> Self {
+        Self {
+            core,
+            _mode: PhantomData,
+        }
+    }
 }
 
-/// Create a whole module around the given mock GPIO port struct. Define structs
-/// for its pins and impl useful things.
-macro_rules! gpio {
-    ($PORT:ident, $port:ident,  [$( ($Pin:ident, $pin:ident, $default_mode:ty) ),+ $(,)* ]) => {
+crate::__mock_pin_impls!(ErasedPin<E>);
+
+/// Implement the methods and traits that all types of mock pins have. The pin
+/// struct must have a `core: PinCore` field and a `_mode: PhantomData` field.
+/// Its error type is either a fixed type, or a generic parameter `E` that
+/// comes after its mode.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mock_pin_impls {
+    ($Pin:ident, $Error:ty) => {
+        $crate::__mock_pin_impls!(@impl $Pin, [], $Error);
+    };
+    ($Pin:ident<E>) => {
+        $crate::__mock_pin_impls!(@impl $Pin, [E], E);
+    };
+    (@impl $Pin:ident, [$($E:ident)?], $Error:ty) => {
+        impl<MODE $(, $E: $crate::mock_hal::PinError)?> $Pin<MODE $(, $E)?> {
+            /// Change the mode of this mock pin.
+            pub fn into_mode<M: $crate::mock_hal::Mode>(self) -> $Pin<M $(, $E)?> {
+                $Pin {
+                    core: self.core.into_mode::<M>(),
+                    _mode: ::core::marker::PhantomData,
+                }
+            }
+
+            /// Change the mode of this mock pin to an output with low and high states.
+            pub fn into_push_pull_output(
+                self,
+            ) -> $Pin<$crate::mock_hal::Output<$crate::mock_hal::PushPull> $(, $E)?> {
+                self.into_mode()
+            }
+
+            /// Change the mode of this mock pin to an output with low and floating states.
+            pub fn into_open_drain_output(
+                self,
+            ) -> $Pin<$crate::mock_hal::Output<$crate::mock_hal::OpenDrain> $(, $E)?> {
+                self.into_mode()
+            }
+
+            /// Change the mode of this mock pin to a floating input.
+            pub fn into_floating_input(
+                self,
+            ) -> $Pin<$crate::mock_hal::Input<$crate::mock_hal::Floating> $(, $E)?> {
+                self.into_mode()
+            }
+
+            /// Change the mode of this mock pin to an input with a pullup resistor.
+            pub fn into_pull_up_input(
+                self,
+            ) -> $Pin<$crate::mock_hal::Input<$crate::mock_hal::PullUp> $(, $E)?> {
+                self.into_mode()
+            }
+
+            /// Change the mode of this mock pin to an input with a pulldown resistor.
+            pub fn into_pull_down_input(
+                self,
+            ) -> $Pin<$crate::mock_hal::Input<$crate::mock_hal::PullDown> $(, $E)?> {
+                self.into_mode()
+            }
+
+            /// The port and pin number of this mock pin.
+            pub fn id(&self) -> $crate::mock_hal::PinId {
+                self.core.id()
+            }
+        }
+
+        impl<O: $crate::mock_hal::OutputMode $(, $E: $crate::mock_hal::PinError)?>
+            $crate::embedded_hal::digital::v2::OutputPin
+            for $Pin<$crate::mock_hal::Output<O> $(, $E)?>
+        {
+            type Error = $Error;
+            /// Drive the mock pin high, or leave it floating if it's open-drain.
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                self.core.record($crate::mock_hal::Operation::SetHigh);
+                self.core.set_drive(O::HIGH);
+                Ok(())
+            }
+            /// Drive the mock pin low.
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                self.core.record($crate::mock_hal::Operation::SetLow);
+                self.core.set_drive($crate::mock_hal::Drive::Low);
+                Ok(())
+            }
+        }
+
+        impl<MODE $(, $E: $crate::mock_hal::PinError)?> $crate::embedded_hal::digital::v2::InputPin
+            for $Pin<$crate::mock_hal::Input<MODE> $(, $E)?>
+        {
+            type Error = $Error;
+            /// Is the mock input pin high? If it's floating, do what the
+            /// wiring's `FloatPolicy` says.
+            fn is_high(&self) -> Result<bool, Self::Error> {
+                self.core.record($crate::mock_hal::Operation::IsHigh);
+                self.core.read_low::<$Error>().map(|low| !low)
+            }
+            /// Is the mock input pin low? If it's floating, do what the
+            /// wiring's `FloatPolicy` says.
+            fn is_low(&self) -> Result<bool, Self::Error> {
+                self.core.record($crate::mock_hal::Operation::IsLow);
+                self.core.read_low()
+            }
+        }
+
+        impl$(<$E: $crate::mock_hal::PinError>)? $crate::embedded_hal::digital::v2::InputPin
+            for $Pin<$crate::mock_hal::Output<$crate::mock_hal::OpenDrain> $(, $E)?>
+        {
+            type Error = $Error;
+            /// Is the wire connected to the open-drain pin high? If it's
+            /// floating, do what the wiring's `FloatPolicy` says.
+            fn is_high(&self) -> Result<bool, Self::Error> {
+                self.core.record($crate::mock_hal::Operation::IsHigh);
+                self.core.read_low::<$Error>().map(|low| !low)
+            }
+            /// Is the wire connected to the open-drain pin low? If it's
+            /// floating, do what the wiring's `FloatPolicy` says.
+            fn is_low(&self) -> Result<bool, Self::Error> {
+                self.core.record($crate::mock_hal::Operation::IsLow);
+                self.core.read_low()
+            }
+        }
+
+        impl<I, O $(, $E)?>
+            $crate::embedded_hal::digital::v2::IoPin<
+                $Pin<$crate::mock_hal::Input<I> $(, $E)?>,
+                $Pin<$crate::mock_hal::Output<O> $(, $E)?>,
+            > for $Pin<$crate::mock_hal::Input<I> $(, $E)?>
+        where
+            I: $crate::mock_hal::InputMode,
+            O: $crate::mock_hal::OutputMode,
+            $($E: $crate::mock_hal::PinError,)?
+        {
+            type Error = $Error;
+
+            fn into_input_pin(self) -> Result<$Pin<$crate::mock_hal::Input<I> $(, $E)?>, Self::Error> {
+                Ok(self)
+            }
+
+            fn into_output_pin(
+                self,
+                state: $crate::embedded_hal::digital::v2::PinState,
+            ) -> Result<$Pin<$crate::mock_hal::Output<O> $(, $E)?>, Self::Error> {
+                use $crate::embedded_hal::digital::v2::OutputPin;
+                let mut pin = self.into_mode::<$crate::mock_hal::Output<O>>();
+                pin.set_state(state)?;
+                Ok(pin)
+            }
+        }
+
+        impl<I, O $(, $E)?>
+            $crate::embedded_hal::digital::v2::IoPin<
+                $Pin<$crate::mock_hal::Input<I> $(, $E)?>,
+                $Pin<$crate::mock_hal::Output<O> $(, $E)?>,
+            > for $Pin<$crate::mock_hal::Output<O> $(, $E)?>
+        where
+            I: $crate::mock_hal::InputMode,
+            O: $crate::mock_hal::OutputMode,
+            $($E: $crate::mock_hal::PinError,)?
+        {
+            type Error = $Error;
+
+            fn into_input_pin(self) -> Result<$Pin<$crate::mock_hal::Input<I> $(, $E)?>, Self::Error> {
+                Ok(self.into_mode())
+            }
+
+            fn into_output_pin(
+                mut self,
+                state: $crate::embedded_hal::digital::v2::PinState,
+            ) -> Result<$Pin<$crate::mock_hal::Output<O> $(, $E)?>, Self::Error> {
+                use $crate::embedded_hal::digital::v2::OutputPin;
+                self.set_state(state)?;
+                Ok(self)
+            }
+        }
+    };
+}
+
+/// Declare a mock GPIO port, like the built-in `GPIOA`.
+///
+/// This defines a unit struct for the port that implements
+/// [`GpioExt`](crate::mock_hal::GpioExt), and a module containing its `Parts`
+/// and a struct for each of its pins. Each pin is listed with its struct name,
+/// its field name in `Parts`, its pin number, and the mode it starts in. The
+/// mode is written with the markers from [`mock_hal`](crate::mock_hal), like
+/// `Input<Floating>`, or with any names in scope where the macro is used.
+///
+/// The port number identifies the port in a [`Wiring`], where port A is 0. It
+/// must be less than [`MAX_PORTS`], and each pin number must be less than
+/// [`PINS_PER_PORT`], or splitting the port will panic.
+///
+/// Pins from any ports can be connected through the same `Wiring`.
+///
+/// The error type of the pins is `Infallible` by default. It can be given
+/// after the port number instead, as any type that implements [`PinError`],
+/// like [`MockError`]. Only a port with a fallible error type can report
+/// reading a floating input as an error, with [`FloatPolicy::Error`].
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use keypad::embedded_hal::digital::v2::{InputPin, IoPin, OutputPin, PinState};
+/// use keypad::mock_hal::{GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+///
+/// mock_port!(GPIOB, gpiob, 1, [
+///     (PB0, pb0, 0, Input<Floating>),
+///     (PB1, pb1, 1, Input<Floating>),
+///     (PB2, pb2, 2, Input<PullDown>),
+/// ]);
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// fn main() {
+///     let a = GPIOA::split_wired(&WIRING);
+///     let b = GPIOB::split_wired(&WIRING);
+///
+///     // A row on port A and a column on port B.
+///     let row = a.pa0.into_pull_up_input();
+///     let mut column = b.pb0.into_open_drain_output();
+///     column.set_low().unwrap();
+///     WIRING.connect(row.id(), column.id());
+///     assert!(row.is_low().unwrap());
+///
+///     // Pull-down inputs read low when nothing drives them.
+///     assert!(b.pb2.is_low().unwrap());
+///
+///     // A pin can switch between input and output at runtime.
+///     let line: gpiob::PB1<Input<PullUp>> = b.pb1.into_pull_up_input();
+///     assert!(line.is_high().unwrap());
+///     let line: gpiob::PB1<Output<OpenDrain>> = line.into_output_pin(PinState::Low).unwrap();
+///     assert!(line.is_low().unwrap());
+///     let line: gpiob::PB1<Input<PullUp>> = line.into_input_pin().unwrap();
+///     assert!(line.is_high().unwrap());
+/// }
+/// ```
+#[macro_export]
+macro_rules! mock_port {
+    ($PORT:ident, $port:ident, $port_number:expr, [$( ($Pin:ident, $pin:ident, $pin_number:expr, $default_mode:ty) ),+ $(,)* ]) => {
+        $crate::mock_port!($PORT, $port, $port_number, ::core::convert::Infallible, [$(($Pin, $pin, $pin_number, $default_mode)),+]);
+    };
+    ($PORT:ident, $port:ident, $port_number:expr, $Error:ty, [$( ($Pin:ident, $pin:ident, $pin_number:expr, $default_mode:ty) ),+ $(,)* ]) => {
+        /// A struct representing a mock port of GPIO pins.
+        #[derive(Debug)]
+        pub struct $PORT;
+
         /// A module containing a mock port of GPIO pins.
         pub mod $port {
-            use super::{State, Input,Output, Floating, PushPull, OpenDrain, GpioExt, PullUp, $PORT};
+            #[allow(unused_imports)]
+            use super::*;
+            #[allow(unused_imports)]
+            use $crate::mock_hal::{Floating, Input, OpenDrain, Output, PullDown, PullUp, PushPull};
+            use $crate::mock_hal::{ErasedPin, GpioExt, Mode, PinCore, PinId, Wiring};
+            use super::$PORT;
             use core::marker::PhantomData;
-            use embedded_hal::digital::v2::{InputPin, OutputPin};
 
             /// The pins of a mock GPIO port
             #[derive(Debug)]
@@ -82,142 +414,65 @@ macro_rules! gpio {
                         )+
                     }
                 }
+
+                fn split_wired(wiring: &'static Wiring) -> Parts {
+                    Self::Parts {
+                        $(
+                            $pin: $Pin {
+                                core: $Pin::<$default_mode>::default().core.wire(Some(wiring)),
+                                _mode: PhantomData,
+                            },
+                        )+
+                    }
+                }
             }
 
             $(
                 /// A mock GPIO pin in a particular mode.
                 #[derive(Debug)]
                 pub struct $Pin<MODE> {
-                    state: State,
+                    core: PinCore,
                     _mode: PhantomData<MODE>,
                 }
 
-
-                impl Default for $Pin<Input<Floating>> {
-                    fn default() -> Self {
-                        Self {
-                            state: State::Float,
-                            _mode: PhantomData,
-                        }
-                    }
-                }
-
-                impl Default for $Pin<Input<PullUp>> {
-                    fn default() -> Self {
-                        Self {
-                            state: State::High,
-                            _mode: PhantomData,
-                        }
-                    }
-                }
-
-                impl Default for $Pin<Output<PushPull>> {
+                impl<MODE: Mode> Default for $Pin<MODE> {
                     fn default() -> Self {
                         Self {
-                            // TODO is default state actually low?
-                            state: State::Low,
-                            _mode: PhantomData,
-                        }
-                    }
-                }
-
-                impl Default for $Pin<Output<OpenDrain>> {
-                    fn default() -> Self {
-                        Self {
-                            state: State::Float,
+                            core: PinCore::new(PinId::new($port_number, $pin_number), MODE::DRIVE),
                             _mode: PhantomData,
                         }
                     }
                 }
 
                 impl<MODE> $Pin<MODE> {
-                    /// Change the mode of this mock pin to an output with low and high states.
-                    pub fn into_push_pull_output(self) -> $Pin<Output<PushPull>> {
-                        $Pin::default()
-                    }
-
-                    /// Change the mode of this mock pin to an output with low and floating states.
-                    pub fn into_open_drain_output(self) -> $Pin<Output<OpenDrain>> {
-                        $Pin::default()
-                    }
-
-                    /// Change the mode of this mock pin to a floating input.
-                    pub fn into_floating_input(self) -> $Pin<Input<Floating>> {
-                        $Pin::default()
-                    }
-
-                    /// Change the mode of this mock pin to an input with a pullup resistor.
-                    pub fn into_pull_up_input(self) -> $Pin<Input<PullUp>> {
-                        $Pin::default()
+                    /// Convert this mock pin into one whose port and pin
+                    /// number are only known at runtime.
+                    pub fn erase(self) -> ErasedPin<MODE, $Error> {
+                        ErasedPin::from_core(self.core)
                     }
                 }
 
-                impl OutputPin for $Pin<Output<PushPull>> {
-                    type Error = core::convert::Infallible;
-                    /// Drive the mock pin high.
-                    fn set_high(&mut self) -> Result<(), Self::Error> {
-                        Ok(self.state = State::High)
-                    }
-                    /// Drive the mock pin low.
-                    fn set_low(&mut self) -> Result<(), Self::Error> {
-                        Ok(self.state = State::Low)
-                    }
-                }
-
-                impl OutputPin for $Pin<Output<OpenDrain>> {
-                    type Error = core::convert::Infallible;
-                    /// Leave the mock pin floating.
-                    fn set_high(&mut self) -> Result<(), Self::Error> {
-                        Ok(self.state = State::Float)
-                    }
-
-                    /// Drive the mock pin low.
-                    fn set_low(&mut self) -> Result<(), Self::Error> {
-                        Ok(self.state = State::Low)
-                    }
-                }
-
-                impl<MODE> InputPin for $Pin<Input<MODE>> {
-                    type Error = core::convert::Infallible;
-                    /// Is the mock input pin high? Panic if it's floating.
-                    fn is_high(&self) -> Result<bool,Self::Error> {
-                        Ok(!self.is_low()?)
-                    }
-                    /// Is the mock input pin low? Panic if it's floating.
-                    fn is_low(&self) -> Result<bool, Self::Error> {
-                        match self.state {
-                            State::Low => Ok(true),
-                            State::High => Ok(false),
-                            State::Float => {
-                                panic!("Tried to read a floating input, value is non-deterministic!")
-                            }
-                        }
-                    }
-                }
+                $crate::__mock_pin_impls!($Pin, $Error);
             )+
         }
     };
 }
 
-/// A struct representing a mock port of GPIO pins.
-#[derive(Debug)]
-pub struct GPIOA;
-
-gpio!( GPIOA, gpioa, [
-    (PA0, pa0, Input<Floating>),
-    (PA1, pa1, Input<Floating>),
-    (PA2, pa2, Input<Floating>),
-    (PA3, pa3, Input<Floating>),
-    (PA4, pa4, Input<Floating>),
-    (PA5, pa5, Input<Floating>),
-    (PA6, pa6, Input<Floating>),
-    (PA7, pa7, Input<Floating>),
-    (PA8, pa8, Input<Floating>),
-    (PA9, pa9, Input<Floating>),
-    (PA10, pa10, Input<Floating>),
-    (PA11, pa11, Input<Floating>),
-    (PA12, pa12, Input<Floating>),
-    (PA13, pa13, Input<Floating>),
-    (PA14, pa14, Input<Floating>),
-    (PA15, pa15, Input<Floating>),
+mock_port!( GPIOA, gpioa, 0, [
+    (PA0, pa0, 0, Input<Floating>),
+    (PA1, pa1, 1, Input<Floating>),
+    (PA2, pa2, 2, Input<Floating>),
+    (PA3, pa3, 3, Input<Floating>),
+    (PA4, pa4, 4, Input<Floating>),
+    (PA5, pa5, 5, Input<Floating>),
+    (PA6, pa6, 6, Input<Floating>),
+    (PA7, pa7, 7, Input<Floating>),
+    (PA8, pa8, 8, Input<Floating>),
+    (PA9, pa9, 9, Input<Floating>),
+    (PA10, pa10, 10, Input<Floating>),
+    (PA11, pa11, 11, Input<Floating>),
+    (PA12, pa12, 12, Input<Floating>),
+    (PA13, pa13, 13, Input<Floating>),
+    (PA14, pa14, 14, Input<Floating>),
+    (PA15, pa15, 15, Input<Floating>),
 ]);
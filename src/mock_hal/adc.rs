@@ -0,0 +1,206 @@
+//! A mock analog-to-digital converter.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::adc::{Channel, OneShot};
+use embedded_hal::digital::v2::OutputPin;
+
+/// The number of channels a [`MockAdc`] has.
+pub const ADC_CHANNELS: usize = 8;
+
+/// A mock ADC, whose channels read whatever values they're given.
+///
+/// Each conversion reports that it would block a configurable number of
+/// times before it finishes, to check that drivers wait for it. The values
+/// are stored in `Cell`s, so they can be changed while a driver owns the
+/// ADC.
+///
+/// ```
+/// use keypad::embedded_hal::adc::OneShot;
+/// use keypad::mock_hal::{AnalogPin, MockAdc};
+///
+/// let mut adc = MockAdc::new().with_latency(1);
+/// let mut pin = AnalogPin::<3>;
+/// adc.set(3, 1234);
+/// assert!(adc.read(&mut pin).is_err());
+/// assert_eq!(adc.read(&mut pin), Ok(1234));
+/// assert_eq!(adc.conversions(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockAdc {
+    values: [Cell<u16>; ADC_CHANNELS],
+    latency: u8,
+    waited: Cell<u8>,
+    conversions: Cell<usize>,
+}
+
+impl MockAdc {
+    /// Create an ADC whose channels all read 0, and whose conversions finish
+    /// straight away.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every conversion report that it would block this many times
+    /// before it finishes.
+    pub fn with_latency(mut self, latency: u8) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Set the value that a channel reads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no such channel.
+    pub fn set(&self, channel: u8, value: u16) {
+        self.values[usize::from(channel)].set(value);
+    }
+
+    /// The value that a channel reads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no such channel.
+    pub fn value(&self, channel: u8) -> u16 {
+        self.values[usize::from(channel)].get()
+    }
+
+    /// The number of conversions that have finished.
+    pub fn conversions(&self) -> usize {
+        self.conversions.get()
+    }
+}
+
+/// A mock analog pin, connected to channel `CHANNEL` of a [`MockAdc`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AnalogPin<const CHANNEL: u8>;
+
+impl<const CHANNEL: u8> Channel<MockAdc> for AnalogPin<CHANNEL> {
+    type ID = u8;
+
+    fn channel() -> u8 {
+        CHANNEL
+    }
+}
+
+impl<const CHANNEL: u8> OneShot<MockAdc, u16, AnalogPin<CHANNEL>> for MockAdc {
+    type Error = Infallible;
+
+    fn read(&mut self, _pin: &mut AnalogPin<CHANNEL>) -> nb::Result<u16, Infallible> {
+        if self.waited.get() < self.latency {
+            self.waited.set(self.waited.get() + 1);
+            return Err(nb::Error::WouldBlock);
+        }
+        self.waited.set(0);
+        self.conversions.set(self.conversions.get() + 1);
+        Ok(self.value(CHANNEL))
+    }
+}
+
+/// The number of inputs a [`MockAnalogMux`] has.
+pub const MUX_INPUTS: usize = 16;
+
+/// A mock 16-channel analog multiplexer, like a CD74HC4067, with its output
+/// connected to an ADC.
+///
+/// The input is chosen by the binary number on its 4 address pins, borrowed
+/// with [`address_pins()`](MockAnalogMux::address_pins), where pin 0 is the
+/// least significant bit. The ADC from [`adc()`](MockAnalogMux::adc) reads
+/// the value of the chosen input through any [`AnalogPin`].
+///
+/// ```
+/// use keypad::embedded_hal::adc::OneShot;
+/// use keypad::embedded_hal::digital::v2::OutputPin;
+/// use keypad::mock_hal::{AnalogPin, MockAnalogMux};
+///
+/// let mux = MockAnalogMux::new();
+/// mux.set(5, 3000);
+/// let [mut s0, _s1, mut s2, _s3] = mux.address_pins();
+/// s0.set_high().unwrap();
+/// s2.set_high().unwrap();
+/// assert_eq!(mux.selected(), 5);
+/// assert_eq!(mux.adc().read(&mut AnalogPin::<0>), Ok(3000));
+/// ```
+#[derive(Debug, Default)]
+pub struct MockAnalogMux {
+    address: Cell<u8>,
+    values: [Cell<u16>; MUX_INPUTS],
+}
+
+impl MockAnalogMux {
+    /// Create a multiplexer whose inputs all read 0, with input 0 chosen.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value of an input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no such input.
+    pub fn set(&self, input: u8, value: u16) {
+        self.values[usize::from(input)].set(value);
+    }
+
+    /// The value of an input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no such input.
+    pub fn value(&self, input: u8) -> u16 {
+        self.values[usize::from(input)].get()
+    }
+
+    /// The input chosen by the address pins.
+    pub fn selected(&self) -> u8 {
+        self.address.get()
+    }
+
+    /// The 4 address pins, least significant bit first.
+    pub fn address_pins(&self) -> [MuxAddressPin<'_>; 4] {
+        [0, 1, 2, 3].map(|bit| MuxAddressPin { mux: self, bit })
+    }
+
+    /// An ADC that reads the chosen input.
+    pub fn adc(&self) -> MuxAdc<'_> {
+        MuxAdc { mux: self }
+    }
+}
+
+/// An address pin of a [`MockAnalogMux`].
+#[derive(Debug)]
+pub struct MuxAddressPin<'a> {
+    mux: &'a MockAnalogMux,
+    bit: u8,
+}
+
+impl<'a> OutputPin for MuxAddressPin<'a> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        let address = &self.mux.address;
+        address.set(address.get() & !(1 << self.bit));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        let address = &self.mux.address;
+        address.set(address.get() | 1 << self.bit);
+        Ok(())
+    }
+}
+
+/// An ADC connected to the output of a [`MockAnalogMux`].
+#[derive(Debug)]
+pub struct MuxAdc<'a> {
+    mux: &'a MockAnalogMux,
+}
+
+impl<'a, const CHANNEL: u8> OneShot<MockAdc, u16, AnalogPin<CHANNEL>> for MuxAdc<'a> {
+    type Error = Infallible;
+
+    fn read(&mut self, _pin: &mut AnalogPin<CHANNEL>) -> nb::Result<u16, Infallible> {
+        Ok(self.mux.value(self.mux.selected()))
+    }
+}
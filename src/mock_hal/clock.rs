@@ -0,0 +1,134 @@
+//! Simulated time.
+
+use super::Wiring;
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+/// A monotonic clock that reads the virtual time of a [`Wiring`].
+///
+/// Virtual time only moves forward when something advances it, either
+/// directly with [`advance_us()`](MockClock::advance_us) or by waiting on a
+/// [`MockDelay`]. Tests of time-based features can run instantly and always
+/// see exactly the same times.
+///
+/// The time is also used by the wiring's [`Noise`](super::Noise) model, so
+/// bouncing and settling can be measured in microseconds instead of reads.
+///
+/// ```
+/// use keypad::embedded_hal::blocking::delay::DelayMs;
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::{GpioExt, Noise, Wiring, GPIOA};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// // Switches bounce for 5 ms after they change.
+/// WIRING.set_noise(Noise {
+///     seed: 42,
+///     bounce_us: 5_000,
+///     ..Noise::default()
+/// });
+/// let clock = WIRING.clock();
+/// let mut delay = WIRING.delay();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let row = pins.pa0.into_pull_up_input();
+/// let mut column = pins.pa1.into_open_drain_output();
+/// column.set_low().unwrap();
+///
+/// WIRING.connect(row.id(), column.id());
+/// let start = clock.now_us();
+/// let bouncing: Vec<bool> = (0..20).map(|_| row.is_low().unwrap()).collect();
+/// assert!(bouncing.contains(&false));
+///
+/// delay.delay_ms(5u8);
+/// assert_eq!(clock.now_us() - start, 5_000);
+/// assert!((0..100).all(|_| row.is_low().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MockClock {
+    wiring: &'static Wiring,
+}
+
+impl MockClock {
+    pub(super) fn new(wiring: &'static Wiring) -> Self {
+        Self { wiring }
+    }
+
+    /// The virtual time in nanoseconds since the wiring was created.
+    pub fn now_ns(&self) -> u64 {
+        self.wiring.time_ns()
+    }
+
+    /// The virtual time in microseconds since the wiring was created.
+    pub fn now_us(&self) -> u64 {
+        self.now_ns() / 1_000
+    }
+
+    /// The virtual time in milliseconds since the wiring was created.
+    pub fn now_ms(&self) -> u64 {
+        self.now_ns() / 1_000_000
+    }
+
+    /// Move virtual time forward.
+    pub fn advance_ns(&self, ns: u64) {
+        self.wiring.advance_ns(ns);
+    }
+
+    /// Move virtual time forward.
+    pub fn advance_us(&self, us: u64) {
+        self.advance_ns(us.saturating_mul(1_000));
+    }
+
+    /// Move virtual time forward.
+    pub fn advance_ms(&self, ms: u64) {
+        self.advance_ns(ms.saturating_mul(1_000_000));
+    }
+}
+
+/// A delay provider that returns immediately, after moving the virtual time
+/// of a [`Wiring`] forward by the requested amount.
+///
+/// It implements the `DelayUs` and `DelayMs` traits for `u8`, `u16` and
+/// `u32`. For nanosecond delays, use the inherent
+/// [`delay_ns()`](MockDelay::delay_ns) method.
+#[derive(Debug, Clone, Copy)]
+pub struct MockDelay {
+    clock: MockClock,
+}
+
+impl MockDelay {
+    pub(super) fn new(wiring: &'static Wiring) -> Self {
+        Self {
+            clock: MockClock::new(wiring),
+        }
+    }
+
+    /// Move virtual time forward by `ns` nanoseconds.
+    pub fn delay_ns(&mut self, ns: u32) {
+        self.clock.advance_ns(u64::from(ns));
+    }
+
+    /// The clock that this delay advances.
+    pub fn clock(&self) -> MockClock {
+        self.clock
+    }
+}
+
+macro_rules! impl_delay {
+    ($($word:ty),+) => {
+        $(
+            impl DelayUs<$word> for MockDelay {
+                fn delay_us(&mut self, us: $word) {
+                    self.clock.advance_us(u64::from(us));
+                }
+            }
+
+            impl DelayMs<$word> for MockDelay {
+                fn delay_ms(&mut self, ms: $word) {
+                    self.clock.advance_ms(u64::from(ms));
+                }
+            }
+        )+
+    };
+}
+
+impl_delay!(u8, u16, u32);
@@ -0,0 +1,355 @@
+//! Mock pins that fail on purpose.
+
+use super::PinId;
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::digital::v2::{InputPin, IoPin, OutputPin, PinState};
+
+/// The error type for mock pins that can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MockError {
+    /// An error injected by a [`Faulty`] pin.
+    Injected,
+    /// A floating input was read, with [`FloatPolicy::Error`].
+    FloatingInput(PinId),
+    /// No mock I2C device has this address.
+    NoAcknowledge(u8),
+    /// A mock I2C device doesn't have this register.
+    InvalidRegister(u8),
+}
+
+impl From<Infallible> for MockError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// An error type that mock pins can use.
+pub trait PinError: Sized {
+    /// The error for reading this floating input, or `None` if this type
+    /// can't represent one.
+    fn floating_input(pin: PinId) -> Option<Self>;
+}
+
+impl PinError for Infallible {
+    fn floating_input(_pin: PinId) -> Option<Self> {
+        None
+    }
+}
+
+impl PinError for MockError {
+    fn floating_input(pin: PinId) -> Option<Self> {
+        Some(MockError::FloatingInput(pin))
+    }
+}
+
+/// What a wired mock input pin does when it's read while floating.
+///
+/// A floating input has no defined level, so reading one is usually a wiring
+/// mistake, like a keypad row without a pull-up resistor. Every floating read
+/// is counted by the [`Wiring`](super::Wiring), unless it panics. The policy
+/// is set for each port with
+/// [`Wiring::set_float_policy()`](super::Wiring::set_float_policy).
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use keypad::mock_hal::{FloatPolicy, Floating, GpioExt, Input, MockError, OpenDrain, Output};
+/// use keypad::mock_hal::Wiring;
+///
+/// mock_port!(GPIOB, gpiob, 1, MockError, [
+///     (PB0, pb0, 0, Input<Floating>),
+///     (PB1, pb1, 1, Input<Floating>),
+///     (PB2, pb2, 2, Input<Floating>),
+/// ]);
+///
+/// // The row is missing its pull-up resistor.
+/// keypad_struct! {
+///     struct Keypad<Error = MockError> {
+///         rows: (
+///             gpiob::PB0<Input<Floating>>,
+///         ),
+///         columns: (
+///             gpiob::PB1<Output<OpenDrain>>,
+///             gpiob::PB2<Output<OpenDrain>>,
+///         ),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// fn main() {
+///     WIRING.set_float_policy(1, FloatPolicy::Error);
+///     let pins = GPIOB::split_wired(&WIRING);
+///     let row = pins.pb0.id();
+///     let keypad = keypad_new!(Keypad {
+///         rows: (pins.pb0,),
+///         columns: (
+///             pins.pb1.into_open_drain_output(),
+///             pins.pb2.into_open_drain_output(),
+///         ),
+///     });
+///
+///     assert_eq!(keypad.scan(), Err(MockError::FloatingInput(row)));
+///
+///     // Or count the mistakes, and carry on as if the row was high.
+///     WIRING.set_float_policy(1, FloatPolicy::Record);
+///     WIRING.clear_floating_reads();
+///     assert!(!keypad.scan().unwrap().any());
+///     assert_eq!(WIRING.floating_reads(), 2);
+///     assert_eq!(WIRING.last_floating_read(), Some(row));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatPolicy {
+    /// Panic. This is the default.
+    Panic,
+    /// Return the error from [`PinError::floating_input()`], or panic if the
+    /// port's error type can't represent it.
+    Error,
+    /// Read a pseudo-random level, from the wiring's
+    /// [`Noise`](super::Noise) seed.
+    Random,
+    /// Read high.
+    Record,
+}
+
+impl FloatPolicy {
+    pub(super) fn to_u8(self) -> u8 {
+        match self {
+            FloatPolicy::Panic => 0,
+            FloatPolicy::Error => 1,
+            FloatPolicy::Random => 2,
+            FloatPolicy::Record => 3,
+        }
+    }
+
+    pub(super) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => FloatPolicy::Error,
+            2 => FloatPolicy::Random,
+            3 => FloatPolicy::Record,
+            _ => FloatPolicy::Panic,
+        }
+    }
+}
+
+impl Default for FloatPolicy {
+    fn default() -> Self {
+        FloatPolicy::Panic
+    }
+}
+
+/// When a [`Faulty`] pin should fail.
+///
+/// Calls are counted from 1, and every method of the `InputPin`, `OutputPin`
+/// and `IoPin` traits counts as one call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Never fail.
+    Never,
+    /// Fail on this call only.
+    Nth(u32),
+    /// Fail on every call after this one.
+    After(u32),
+    /// Fail on call `n` if element `n - 1` of the script is true. Calls past
+    /// the end of the script succeed.
+    Script(&'static [bool]),
+}
+
+impl Schedule {
+    pub(super) fn fails(&self, call: u32) -> bool {
+        match *self {
+            Schedule::Never => false,
+            Schedule::Nth(n) => call == n,
+            Schedule::After(n) => call > n,
+            Schedule::Script(script) => script.get(call as usize - 1).copied().unwrap_or(false),
+        }
+    }
+}
+
+/// A wrapper around a mock pin that returns an error according to a
+/// [`Schedule`].
+///
+/// When a call fails, the wrapped pin isn't touched: a failed `set_low()`
+/// leaves the pin as it was, and a failed read doesn't read it. A failed
+/// mode switch drops the pin, like a HAL that can't give it back. The error
+/// type `E` can be anything that the wrapped pin's error converts into, like
+/// [`MockError`].
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use keypad::embedded_hal::digital::v2::InputPin;
+/// use keypad::mock_hal::{self, Faulty, GpioExt, Input, MockError, OpenDrain, Output, PullUp};
+/// use keypad::mock_hal::{Schedule, Wiring, GPIOA};
+///
+/// type Fallible<P> = Faulty<P, MockError>;
+///
+/// keypad_struct! {
+///     struct Keypad<Error = MockError> {
+///         rows: (
+///             Fallible<mock_hal::gpioa::PA0<Input<PullUp>>>,
+///             Fallible<mock_hal::gpioa::PA1<Input<PullUp>>>,
+///         ),
+///         columns: (
+///             Fallible<mock_hal::gpioa::PA2<Output<OpenDrain>>>,
+///             Fallible<mock_hal::gpioa::PA3<Output<OpenDrain>>>,
+///         ),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// fn main() {
+///     let pins = GPIOA::split_wired(&WIRING);
+///     let matrix = WIRING.matrix([pins.pa0.id(), pins.pa1.id()], [pins.pa2.id(), pins.pa3.id()]);
+///
+///     let keypad = keypad_new!(Keypad {
+///         rows: (
+///             Faulty::new(pins.pa0.into_pull_up_input(), MockError::Injected, Schedule::Nth(1)),
+///             Faulty::new(pins.pa1.into_pull_up_input(), MockError::Injected, Schedule::Never),
+///         ),
+///         columns: (
+///             Faulty::new(pins.pa2.into_open_drain_output(), MockError::Injected, Schedule::Never),
+///             Faulty::new(pins.pa3.into_open_drain_output(), MockError::Injected, Schedule::Never),
+///         ),
+///     });
+///     let keys = keypad.decompose();
+///
+///     // Reading the first row fails once, and the error is passed on.
+///     assert_eq!(keys[0][0].is_low(), Err(MockError::Injected));
+///
+///     // The first column was released even though the read failed. If it was
+///     // still low, pressing the key at (1, 0) would make the key at (1, 1)
+///     // look pressed too.
+///     matrix.press(1, 0);
+///     assert_eq!(keys[1][1].is_low(), Ok(false));
+///     assert_eq!(keys[1][0].is_low(), Ok(true));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Faulty<P, E> {
+    pin: P,
+    error: E,
+    schedule: Schedule,
+    calls: Cell<u32>,
+}
+
+impl<P, E> Faulty<P, E> {
+    /// Wrap a mock pin, returning `error` whenever the schedule says to fail.
+    pub fn new(pin: P, error: E, schedule: Schedule) -> Self {
+        Self {
+            pin,
+            error,
+            schedule,
+            calls: Cell::new(0),
+        }
+    }
+
+    /// The wrapped pin.
+    pub fn inner(&self) -> &P {
+        &self.pin
+    }
+
+    /// Unwrap the pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+
+    /// The number of calls so far.
+    pub fn calls(&self) -> u32 {
+        self.calls.get()
+    }
+
+    /// Change the schedule, and start counting calls from 1 again.
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+        self.calls.set(0);
+    }
+
+    /// Count one call, and return the error if it should fail.
+    fn call(&self) -> Result<(), E>
+    where
+        E: Clone,
+    {
+        let call = self.calls.get().saturating_add(1);
+        self.calls.set(call);
+        if self.schedule.fails(call) {
+            Err(self.error.clone())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<P, E> InputPin for Faulty<P, E>
+where
+    P: InputPin,
+    E: Clone + From<P::Error>,
+{
+    type Error = E;
+
+    fn is_high(&self) -> Result<bool, E> {
+        self.call()?;
+        Ok(self.pin.is_high()?)
+    }
+
+    fn is_low(&self) -> Result<bool, E> {
+        self.call()?;
+        Ok(self.pin.is_low()?)
+    }
+}
+
+impl<P, E> OutputPin for Faulty<P, E>
+where
+    P: OutputPin,
+    E: Clone + From<P::Error>,
+{
+    type Error = E;
+
+    fn set_high(&mut self) -> Result<(), E> {
+        self.call()?;
+        Ok(self.pin.set_high()?)
+    }
+
+    fn set_low(&mut self) -> Result<(), E> {
+        self.call()?;
+        Ok(self.pin.set_low()?)
+    }
+}
+
+impl<P, I, O, E, PE> IoPin<Faulty<I, E>, Faulty<O, E>> for Faulty<P, E>
+where
+    P: IoPin<I, O, Error = PE>,
+    I: InputPin<Error = PE> + IoPin<I, O, Error = PE>,
+    O: OutputPin<Error = PE> + IoPin<I, O, Error = PE>,
+    E: Clone + From<PE>,
+{
+    type Error = E;
+
+    fn into_input_pin(self) -> Result<Faulty<I, E>, E> {
+        self.call()?;
+        Ok(Faulty {
+            pin: self.pin.into_input_pin()?,
+            error: self.error,
+            schedule: self.schedule,
+            calls: self.calls,
+        })
+    }
+
+    fn into_output_pin(self, state: PinState) -> Result<Faulty<O, E>, E> {
+        self.call()?;
+        Ok(Faulty {
+            pin: self.pin.into_output_pin(state)?,
+            error: self.error,
+            schedule: self.schedule,
+            calls: self.calls,
+        })
+    }
+}
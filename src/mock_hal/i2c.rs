@@ -0,0 +1,656 @@
+//! Mock I2C devices.
+//!
+//! Each mock device is its own I2C bus with one device on it, which only
+//! acknowledges its own address. Keys are pressed by connecting two of the
+//! device's pins with [`connect()`](MockPcf8574::connect). A pressed key
+//! pulls a pin low if the other pin is driven low. The devices use `Cell`s,
+//! so keys can be pressed while a driver owns the bus.
+//!
+//! The expanders can be made to fail reads with [`MockError::Injected`],
+//! according to a [`Schedule`] that counts reads from 1.
+//!
+//! The expanders also implement the I2C traits through a shared reference,
+//! so a driver can use `&device` as its bus while an [`InterruptPin`] reads
+//! the same device's interrupt output.
+
+use super::{MockError, Schedule};
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+/// The levels of pins that are connected in pairs by `switches`, where
+/// `switches[a]` has bit `b` set if pins `a` and `b` are connected, and the
+/// pins in `low` are driven low.
+fn connected_low(switches: &[Cell<u16>], low: u16) -> u16 {
+    let mut low = low;
+    loop {
+        let mut next = low;
+        for (pin, switch) in switches.iter().enumerate() {
+            if low & 1 << pin != 0 {
+                next |= switch.get();
+            }
+        }
+        if next == low {
+            return low;
+        }
+        low = next;
+    }
+}
+
+/// Count one read, and fail it if the schedule says to.
+fn fail_read(faults: &Cell<Schedule>, reads: &Cell<u32>) -> Result<(), MockError> {
+    let read = reads.get().saturating_add(1);
+    reads.set(read);
+    if faults.get().fails(read) {
+        Err(MockError::Injected)
+    } else {
+        Ok(())
+    }
+}
+
+fn set_switch(switches: &[Cell<u16>], a: u8, b: u8, closed: bool) {
+    let (a, b) = (usize::from(a), usize::from(b));
+    assert!(
+        a < switches.len() && b < switches.len(),
+        "mock device pin out of range"
+    );
+    for &(from, to) in &[(a, b), (b, a)] {
+        let bits = switches[from].get();
+        switches[from].set(if closed {
+            bits | 1 << to
+        } else {
+            bits & !(1 << to)
+        });
+    }
+}
+
+/// A mock PCF8574 8-bit I2C I/O expander.
+///
+/// Its pins P0 to P7 are numbered 0 to 7. Writing a byte sets the output
+/// latch: a 0 bit drives the pin low, and a 1 bit leaves it high through a
+/// weak pull-up, so it can be used as an input. Reading a byte returns the
+/// level of every pin.
+///
+/// The interrupt output is asserted when the pin levels are different from
+/// the last time they were read.
+#[derive(Debug)]
+pub struct MockPcf8574 {
+    address: u8,
+    latch: Cell<u8>,
+    switches: [Cell<u16>; 8],
+    last_read: Cell<u8>,
+    transfers: Cell<usize>,
+    read_faults: Cell<Schedule>,
+    reads: Cell<u32>,
+}
+
+impl MockPcf8574 {
+    /// Create a mock device at this address, with every pin high.
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            latch: Cell::new(0xFF),
+            switches: Default::default(),
+            last_read: Cell::new(0xFF),
+            transfers: Cell::new(0),
+            read_faults: Cell::new(Schedule::Never),
+            reads: Cell::new(0),
+        }
+    }
+
+    /// Connect two pins, like pressing a key between them.
+    pub fn connect(&self, a: u8, b: u8) {
+        set_switch(&self.switches, a, b, true);
+    }
+
+    /// Disconnect two pins, like releasing a key between them.
+    pub fn disconnect(&self, a: u8, b: u8) {
+        set_switch(&self.switches, a, b, false);
+    }
+
+    /// The output latch, as last written.
+    pub fn latch(&self) -> u8 {
+        self.latch.get()
+    }
+
+    /// The level of every pin.
+    pub fn levels(&self) -> u8 {
+        !connected_low(&self.switches, u16::from(!self.latch.get())) as u8
+    }
+
+    /// Is the interrupt output asserted?
+    pub fn interrupt(&self) -> bool {
+        self.levels() != self.last_read.get()
+    }
+
+    /// A mock input pin connected to the active-low interrupt output.
+    pub fn interrupt_pin(&self) -> InterruptPin<'_, Self> {
+        InterruptPin { device: self }
+    }
+
+    /// The number of I2C transfers addressed to the device.
+    pub fn transfers(&self) -> usize {
+        self.transfers.get()
+    }
+
+    /// Make reads fail according to this schedule, counting from 1 again.
+    pub fn fail_reads(&self, schedule: Schedule) {
+        self.read_faults.set(schedule);
+        self.reads.set(0);
+    }
+
+    fn check_address(&self, address: u8) -> Result<(), MockError> {
+        if address == self.address {
+            Ok(())
+        } else {
+            Err(MockError::NoAcknowledge(address))
+        }
+    }
+}
+
+impl Write for &MockPcf8574 {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        self.check_address(address)?;
+        self.transfers.set(self.transfers.get() + 1);
+        if let Some(&byte) = bytes.last() {
+            self.latch.set(byte);
+        }
+        Ok(())
+    }
+}
+
+impl Read for &MockPcf8574 {
+    type Error = MockError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), MockError> {
+        self.check_address(address)?;
+        self.transfers.set(self.transfers.get() + 1);
+        fail_read(&self.read_faults, &self.reads)?;
+        for byte in buffer.iter_mut() {
+            *byte = self.levels();
+        }
+        self.last_read.set(self.levels());
+        Ok(())
+    }
+}
+
+impl Write for MockPcf8574 {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        Write::write(&mut &*self, address, bytes)
+    }
+}
+
+impl Read for MockPcf8574 {
+    type Error = MockError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), MockError> {
+        Read::read(&mut &*self, address, buffer)
+    }
+}
+
+/// The number of registers in a [`MockMcp23017`].
+const MCP23017_REGISTERS: usize = 0x16;
+
+/// A mock MCP23017 16-bit I2C I/O expander, with its registers in the
+/// default `IOCON.BANK = 0` layout.
+///
+/// Its pins GPA0 to GPA7 are numbered 0 to 7, and GPB0 to GPB7 are numbered
+/// 8 to 15. A write starts with a register address, and the address moves
+/// to the next register after every byte. The `IODIR`, `GPPU`, `GPIO`,
+/// `OLAT`, `GPINTEN`, `DEFVAL`, `INTCON` and `INTCAP` registers work like
+/// the real ones. Inputs without pull-ups, that aren't connected to a low
+/// pin, read high.
+///
+/// Only port A has an interrupt output.
+#[derive(Debug)]
+pub struct MockMcp23017 {
+    address: u8,
+    registers: [Cell<u8>; MCP23017_REGISTERS],
+    switches: [Cell<u16>; 16],
+    /// The port A levels when the interrupt was last cleared.
+    captured: Cell<u8>,
+    transfers: Cell<usize>,
+    read_faults: Cell<Schedule>,
+    reads: Cell<u32>,
+}
+
+impl MockMcp23017 {
+    /// The I/O direction register of port A. Port B's is the next one.
+    pub const IODIRA: u8 = 0x00;
+    /// The interrupt-on-change enable register of port A.
+    pub const GPINTENA: u8 = 0x04;
+    /// The default compare value register of port A.
+    pub const DEFVALA: u8 = 0x06;
+    /// The interrupt control register of port A.
+    pub const INTCONA: u8 = 0x08;
+    /// The pull-up register of port A.
+    pub const GPPUA: u8 = 0x0C;
+    /// The interrupt capture register of port A.
+    pub const INTCAPA: u8 = 0x10;
+    /// The port register of port A.
+    pub const GPIOA: u8 = 0x12;
+    /// The output latch register of port A.
+    pub const OLATA: u8 = 0x14;
+
+    /// Create a mock device at this address, in its power-on state: every
+    /// pin is an input without a pull-up.
+    pub fn new(address: u8) -> Self {
+        let device = Self {
+            address,
+            registers: Default::default(),
+            switches: Default::default(),
+            captured: Cell::new(0xFF),
+            transfers: Cell::new(0),
+            read_faults: Cell::new(Schedule::Never),
+            reads: Cell::new(0),
+        };
+        device.registers[usize::from(Self::IODIRA)].set(0xFF);
+        device.registers[usize::from(Self::IODIRA) + 1].set(0xFF);
+        device
+    }
+
+    /// Connect two pins, like pressing a key between them.
+    pub fn connect(&self, a: u8, b: u8) {
+        set_switch(&self.switches, a, b, true);
+    }
+
+    /// Disconnect two pins, like releasing a key between them.
+    pub fn disconnect(&self, a: u8, b: u8) {
+        set_switch(&self.switches, a, b, false);
+    }
+
+    /// The value of a register. Reading `GPIO` this way doesn't clear the
+    /// interrupt.
+    pub fn register(&self, register: u8) -> u8 {
+        match register {
+            Self::GPIOA => self.levels() as u8,
+            r if r == Self::GPIOA + 1 => (self.levels() >> 8) as u8,
+            r => self.registers[usize::from(r)].get(),
+        }
+    }
+
+    /// The level of every pin, with port A in the low byte.
+    pub fn levels(&self) -> u16 {
+        let outputs = !self.pair(Self::IODIRA);
+        let low = outputs & !self.pair(Self::OLATA);
+        !connected_low(&self.switches, low)
+    }
+
+    /// Is the port A interrupt output asserted?
+    pub fn interrupt(&self) -> bool {
+        let levels = self.levels() as u8;
+        let compare = self.reg(Self::INTCONA);
+        let against = (compare & self.reg(Self::DEFVALA)) | (!compare & self.captured.get());
+        (levels ^ against) & self.reg(Self::GPINTENA) != 0
+    }
+
+    /// A mock input pin connected to the active-low port A interrupt output.
+    pub fn interrupt_pin(&self) -> InterruptPin<'_, Self> {
+        InterruptPin { device: self }
+    }
+
+    /// The number of I2C transfers addressed to the device.
+    pub fn transfers(&self) -> usize {
+        self.transfers.get()
+    }
+
+    /// Make reads fail according to this schedule, counting from 1 again.
+    /// A failed read still writes the register address.
+    pub fn fail_reads(&self, schedule: Schedule) {
+        self.read_faults.set(schedule);
+        self.reads.set(0);
+    }
+
+    fn reg(&self, register: u8) -> u8 {
+        self.registers[usize::from(register)].get()
+    }
+
+    /// A register of port A and the same register of port B.
+    fn pair(&self, register: u8) -> u16 {
+        u16::from(self.reg(register)) | u16::from(self.reg(register + 1)) << 8
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        let value = self.register(register);
+        if register == Self::GPIOA || register == Self::INTCAPA {
+            if self.interrupt() {
+                self.registers[usize::from(Self::INTCAPA)].set(self.levels() as u8);
+            }
+            self.captured.set(self.levels() as u8);
+        }
+        value
+    }
+
+    fn write_register(&self, register: u8, value: u8) {
+        match register {
+            // Writing the port register writes the output latch.
+            r if r == Self::GPIOA || r == Self::GPIOA + 1 => {
+                self.registers[usize::from(r + 2)].set(value)
+            }
+            r if r == Self::INTCAPA || r == Self::INTCAPA + 1 => {}
+            r => self.registers[usize::from(r)].set(value),
+        }
+    }
+
+    fn check(&self, address: u8, register: u8) -> Result<(), MockError> {
+        if address != self.address {
+            Err(MockError::NoAcknowledge(address))
+        } else if usize::from(register) >= MCP23017_REGISTERS {
+            Err(MockError::InvalidRegister(register))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl MockMcp23017 {
+    fn write_registers(&self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        if let Some((&first, data)) = bytes.split_first() {
+            self.check(address, first)?;
+            for (register, &value) in (first..).zip(data) {
+                self.check(address, register)?;
+                self.write_register(register, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for &MockMcp23017 {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        self.transfers.set(self.transfers.get() + 1);
+        self.write_registers(address, bytes)
+    }
+}
+
+impl WriteRead for &MockMcp23017 {
+    type Error = MockError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), MockError> {
+        self.transfers.set(self.transfers.get() + 1);
+        let first = bytes.first().copied().unwrap_or(0);
+        self.write_registers(address, bytes)?;
+        fail_read(&self.read_faults, &self.reads)?;
+        for (register, byte) in (first..).zip(buffer.iter_mut()) {
+            self.check(address, register)?;
+            *byte = self.read_register(register);
+        }
+        Ok(())
+    }
+}
+
+impl Write for MockMcp23017 {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        Write::write(&mut &*self, address, bytes)
+    }
+}
+
+impl WriteRead for MockMcp23017 {
+    type Error = MockError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), MockError> {
+        WriteRead::write_read(&mut &*self, address, bytes, buffer)
+    }
+}
+
+/// A mock input pin connected to the active-low interrupt output of a
+/// [`MockPcf8574`] or [`MockMcp23017`].
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptPin<'a, D> {
+    device: &'a D,
+}
+
+impl<'a> InputPin for InterruptPin<'a, MockPcf8574> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(!self.device.interrupt())
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(self.device.interrupt())
+    }
+}
+
+impl<'a> InputPin for InterruptPin<'a, MockMcp23017> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(!self.device.interrupt())
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(self.device.interrupt())
+    }
+}
+
+/// The number of registers in a [`MockTca8418`].
+const TCA8418_REGISTERS: usize = 0x2F;
+
+/// The number of events the FIFO of a [`MockTca8418`] can hold.
+const TCA8418_FIFO: usize = 10;
+
+/// A mock TCA8418 keypad scanner.
+///
+/// Keys are pressed and released with [`press()`](MockTca8418::press) and
+/// [`release()`](MockTca8418::release), and only keys whose row and column
+/// are enabled in the `KP_GPIO` registers are noticed. Each change queues a
+/// key event, like the real chip does after debouncing it: bit 7 is set for
+/// a press, and the low 7 bits are `row * 10 + column + 1`.
+///
+/// Reading `KEY_EVENT_A` takes the oldest event out of the FIFO, or gives 0
+/// if it's empty. The FIFO holds 10 events. When it's full, a new event sets
+/// the overflow bit of `INT_STAT`, and is dropped, unless the
+/// `OVR_FLOW_M` bit of `CFG` is set, in which case the oldest event is
+/// dropped instead. Bits of `INT_STAT` are cleared by writing 1s to them,
+/// but the key event bit is set again straight away while the FIFO isn't
+/// empty.
+/// The register address only moves to the next register after every byte if
+/// the `AI` bit of `CFG` is set.
+#[derive(Debug)]
+pub struct MockTca8418 {
+    address: u8,
+    registers: [Cell<u8>; TCA8418_REGISTERS],
+    fifo: [Cell<u8>; TCA8418_FIFO],
+    queued: Cell<usize>,
+}
+
+impl MockTca8418 {
+    /// The configuration register.
+    pub const CFG: u8 = 0x01;
+    /// The interrupt status register.
+    pub const INT_STAT: u8 = 0x02;
+    /// The key lock and event counter register.
+    pub const KEY_LCK_EC: u8 = 0x03;
+    /// The head of the key event FIFO.
+    pub const KEY_EVENT_A: u8 = 0x04;
+    /// The register selecting which rows are part of the keypad.
+    pub const KP_GPIO1: u8 = 0x1D;
+    /// The register selecting which of columns 0 to 7 are part of the keypad.
+    pub const KP_GPIO2: u8 = 0x1E;
+    /// The register selecting which of columns 8 and 9 are part of the
+    /// keypad.
+    pub const KP_GPIO3: u8 = 0x1F;
+
+    /// The `CFG` bit enabling auto-increment.
+    pub const CFG_AI: u8 = 0x80;
+    /// The `CFG` bit making a full FIFO drop its oldest event.
+    pub const CFG_OVR_FLOW_M: u8 = 0x20;
+    /// The `CFG` bit enabling the overflow interrupt.
+    pub const CFG_OVR_FLOW_IEN: u8 = 0x08;
+    /// The `CFG` bit enabling the key event interrupt.
+    pub const CFG_KE_IEN: u8 = 0x01;
+    /// The `INT_STAT` bit set when the FIFO overflows.
+    pub const OVR_FLOW_INT: u8 = 0x08;
+    /// The `INT_STAT` bit set when a key event is queued.
+    pub const K_INT: u8 = 0x01;
+
+    /// Create a mock device at this address, in its power-on state: no keys
+    /// are part of the keypad, and every interrupt is disabled.
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            registers: [(); TCA8418_REGISTERS].map(|_| Cell::new(0)),
+            fifo: Default::default(),
+            queued: Cell::new(0),
+        }
+    }
+
+    /// Press the key at this row and column.
+    pub fn press(&self, row: u8, column: u8) {
+        self.queue(row, column, 0x80);
+    }
+
+    /// Release the key at this row and column.
+    pub fn release(&self, row: u8, column: u8) {
+        self.queue(row, column, 0x00);
+    }
+
+    /// The number of events in the FIFO.
+    pub fn queued(&self) -> usize {
+        self.queued.get()
+    }
+
+    /// The value of a register. Reading `KEY_EVENT_A` this way doesn't take
+    /// an event out of the FIFO.
+    pub fn register(&self, register: u8) -> u8 {
+        match register {
+            Self::KEY_LCK_EC => self.queued.get() as u8,
+            Self::KEY_EVENT_A => self.fifo[0].get(),
+            r => self.registers[usize::from(r)].get(),
+        }
+    }
+
+    /// Is the interrupt output asserted?
+    pub fn interrupt(&self) -> bool {
+        let config = self.registers[usize::from(Self::CFG)].get();
+        let status = self.registers[usize::from(Self::INT_STAT)].get();
+        (status & Self::K_INT != 0 && config & Self::CFG_KE_IEN != 0)
+            || (status & Self::OVR_FLOW_INT != 0 && config & Self::CFG_OVR_FLOW_IEN != 0)
+    }
+
+    fn queue(&self, row: u8, column: u8, press: u8) {
+        assert!(row < 8 && column < 10, "mock keypad key out of range");
+        let rows = self.registers[usize::from(Self::KP_GPIO1)].get();
+        let columns = u16::from(self.registers[usize::from(Self::KP_GPIO2)].get())
+            | u16::from(self.registers[usize::from(Self::KP_GPIO3)].get()) << 8;
+        if rows & 1 << row == 0 || columns & 1 << column == 0 {
+            return;
+        }
+        let status = &self.registers[usize::from(Self::INT_STAT)];
+        let mut queued = self.queued.get();
+        if queued == TCA8418_FIFO {
+            status.set(status.get() | Self::OVR_FLOW_INT);
+            if self.registers[usize::from(Self::CFG)].get() & Self::CFG_OVR_FLOW_M == 0 {
+                return;
+            }
+            self.pop();
+            queued -= 1;
+        }
+        self.fifo[queued].set(press | (row * 10 + column + 1));
+        self.queued.set(queued + 1);
+        status.set(status.get() | Self::K_INT);
+    }
+
+    fn pop(&self) -> u8 {
+        let event = self.fifo[0].get();
+        for i in 1..TCA8418_FIFO {
+            self.fifo[i - 1].set(self.fifo[i].get());
+        }
+        self.fifo[TCA8418_FIFO - 1].set(0);
+        self.queued.set(self.queued.get().saturating_sub(1));
+        event
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        if register == Self::KEY_EVENT_A {
+            self.pop()
+        } else {
+            self.register(register)
+        }
+    }
+
+    fn write_register(&self, register: u8, value: u8) {
+        match register {
+            Self::INT_STAT => {
+                let status = &self.registers[usize::from(Self::INT_STAT)];
+                let mut cleared = status.get() & !value;
+                if self.queued.get() > 0 {
+                    cleared |= Self::K_INT;
+                }
+                status.set(cleared);
+            }
+            Self::KEY_LCK_EC | Self::KEY_EVENT_A => {}
+            r => self.registers[usize::from(r)].set(value),
+        }
+    }
+
+    /// The registers accessed by a transfer starting at `first`.
+    fn addresses(&self, first: u8) -> impl Iterator<Item = u8> {
+        let step = u8::from(self.registers[usize::from(Self::CFG)].get() & Self::CFG_AI != 0);
+        (0..).map(move |i: u8| first.wrapping_add(i.wrapping_mul(step)))
+    }
+
+    fn check(&self, address: u8, register: u8) -> Result<(), MockError> {
+        if address != self.address {
+            Err(MockError::NoAcknowledge(address))
+        } else if register == 0 || usize::from(register) >= TCA8418_REGISTERS {
+            Err(MockError::InvalidRegister(register))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Write for MockTca8418 {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        if let Some((&first, data)) = bytes.split_first() {
+            self.check(address, first)?;
+            for (register, &value) in self.addresses(first).zip(data) {
+                self.check(address, register)?;
+                self.write_register(register, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WriteRead for MockTca8418 {
+    type Error = MockError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), MockError> {
+        let first = bytes.first().copied().unwrap_or(0);
+        self.write(address, bytes)?;
+        for (register, byte) in self.addresses(first).zip(buffer.iter_mut()) {
+            self.check(address, register)?;
+            *byte = self.read_register(register);
+        }
+        Ok(())
+    }
+}
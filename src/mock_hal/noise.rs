@@ -0,0 +1,95 @@
+//! Simulated contact bounce and electrical noise.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Settings for the imperfections a [`Wiring`](super::Wiring) adds to its
+/// signals.
+///
+/// Durations can be measured in reads, where each read of a wired mock input
+/// pin moves the simulation forward by one step, or in microseconds of the
+/// wiring's virtual [`MockClock`](super::MockClock) time. If both are given,
+/// the effect lasts until both have passed. The noise is pseudo-random, but the
+/// same seed and the same sequence of pin operations and delays will always
+/// produce the same readings.
+///
+/// The default is no noise at all.
+///
+/// ```
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::{GpioExt, Noise, Wiring, GPIOA};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// WIRING.set_noise(Noise {
+///     seed: 1234,
+///     bounce_reads: 8,
+///     ..Noise::default()
+/// });
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let row = pins.pa0.into_pull_up_input();
+/// let mut column = pins.pa1.into_open_drain_output();
+/// column.set_low().unwrap();
+///
+/// // Pressing the key makes it bounce for 8 reads...
+/// WIRING.connect(row.id(), column.id());
+/// let bouncing: Vec<bool> = (0..8).map(|_| row.is_low().unwrap()).collect();
+/// assert!(bouncing.contains(&true) && bouncing.contains(&false));
+///
+/// // ...and then it settles.
+/// assert!((0..100).all(|_| row.is_low().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Noise {
+    /// The seed for the pseudo-random number generator. A seed of 0 is
+    /// replaced by 1.
+    pub seed: u32,
+    /// For how many reads a switch bounces after it's opened or closed. While
+    /// it bounces, it's randomly open or closed on every read.
+    pub bounce_reads: u32,
+    /// For how many microseconds of virtual time a switch bounces after it's
+    /// opened or closed.
+    pub bounce_us: u32,
+    /// The chance that any single read of a pin returns the wrong level, out
+    /// of 65536.
+    pub glitch_chance: u16,
+    /// For how many reads a wire keeps its old level after one of the pins
+    /// connected to it changes how it's driven, like a wire with a lot of
+    /// capacitance.
+    pub settle_reads: u32,
+    /// For how many microseconds of virtual time a wire keeps its old level
+    /// after one of the pins connected to it changes how it's driven.
+    pub settle_us: u32,
+}
+
+/// A xorshift pseudo-random number generator that can be stored in a static.
+#[derive(Debug)]
+pub(super) struct Rng {
+    state: AtomicU32,
+}
+
+impl Rng {
+    pub(super) const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(1),
+        }
+    }
+
+    pub(super) fn seed(&self, seed: u32) {
+        self.state.store(seed.max(1), Ordering::Relaxed);
+    }
+
+    pub(super) fn next_u32(&self) -> u32 {
+        let mut x = self.state.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state.store(x, Ordering::Relaxed);
+        x
+    }
+
+    /// Return true with a probability of `chance` out of 65536.
+    pub(super) fn chance(&self, chance: u32) -> bool {
+        chance > 0 && self.next_u32() >> 16 < chance
+    }
+}
@@ -0,0 +1,159 @@
+//! The behaviour shared by all mock pins.
+
+use super::fault::{FloatPolicy, PinError};
+use super::wiring::Drive;
+use super::{
+    Floating, Input, OpenDrain, Operation, Output, PinId, PullDown, PullUp, PushPull, State, Wiring,
+};
+
+/// A mode that a mock pin can be put in, like `Input<PullUp>`.
+pub trait Mode {
+    /// How a pin in this mode drives its wire when it's first put in it.
+    #[doc(hidden)]
+    const DRIVE: Drive;
+}
+
+/// The resistor used by a mock input pin, like `PullUp`.
+pub trait InputMode {
+    /// How an input pin with this resistor drives its wire.
+    #[doc(hidden)]
+    const DRIVE: Drive;
+}
+
+/// The driver used by a mock output pin, like `OpenDrain`.
+///
+/// A push-pull output starts low, and an open-drain output starts floating,
+/// so no column of a freshly made keypad is selected before it's scanned.
+pub trait OutputMode {
+    /// How an output pin with this driver drives its wire when it's set high.
+    #[doc(hidden)]
+    const HIGH: Drive;
+    /// How an output pin with this driver drives its wire when it's first
+    /// put in output mode.
+    #[doc(hidden)]
+    const INITIAL: Drive;
+}
+
+impl InputMode for Floating {
+    const DRIVE: Drive = Drive::Float;
+}
+
+impl InputMode for PullUp {
+    const DRIVE: Drive = Drive::PullUp;
+}
+
+impl InputMode for PullDown {
+    const DRIVE: Drive = Drive::PullDown;
+}
+
+impl OutputMode for PushPull {
+    const HIGH: Drive = Drive::High;
+    const INITIAL: Drive = Drive::Low;
+}
+
+impl OutputMode for OpenDrain {
+    const HIGH: Drive = Drive::Float;
+    const INITIAL: Drive = Drive::Float;
+}
+
+impl<M: InputMode> Mode for Input<M> {
+    const DRIVE: Drive = M::DRIVE;
+}
+
+impl<M: OutputMode> Mode for Output<M> {
+    const DRIVE: Drive = M::INITIAL;
+}
+
+/// The state of a mock pin that doesn't depend on its type. It's only public
+/// so that it can be used by the [`mock_port!`](crate::mock_port) macro.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct PinCore {
+    id: PinId,
+    drive: Drive,
+    wiring: Option<&'static Wiring>,
+}
+
+impl PinCore {
+    pub fn new(id: PinId, drive: Drive) -> Self {
+        Self {
+            id,
+            drive,
+            wiring: None,
+        }
+    }
+
+    /// Connect the pin to the wiring, if there is one.
+    pub fn wire(mut self, wiring: Option<&'static Wiring>) -> Self {
+        self.wiring = wiring;
+        self.set_drive(self.drive);
+        self
+    }
+
+    /// The same pin in a new mode.
+    pub fn into_mode<M: Mode>(self) -> Self {
+        Self::new(self.id, M::DRIVE).wire(self.wiring)
+    }
+
+    pub fn id(&self) -> PinId {
+        self.id
+    }
+
+    pub fn set_drive(&mut self, drive: Drive) {
+        self.drive = drive;
+        if let Some(wiring) = self.wiring {
+            wiring.drive(self.id, drive);
+        }
+    }
+
+    /// Add an operation to the wiring's trace.
+    pub fn record(&self, operation: Operation) {
+        if let Some(wiring) = self.wiring {
+            wiring.trace().record(self.id, operation);
+        }
+    }
+
+    /// Is the pin low? If it's floating, do what the wiring's policy says,
+    /// or panic if it's not wired.
+    pub fn read_low<E: PinError>(&self) -> Result<bool, E> {
+        match self.state() {
+            State::Low => Ok(true),
+            State::High => Ok(false),
+            State::Float => self.read_floating(),
+        }
+    }
+
+    fn read_floating<E: PinError>(&self) -> Result<bool, E> {
+        let wiring = match self.wiring {
+            Some(wiring) => wiring,
+            None => self.floating_panic(),
+        };
+        match wiring.read_floating(self.id) {
+            FloatPolicy::Panic => self.floating_panic(),
+            FloatPolicy::Error => match E::floating_input(self.id) {
+                Some(error) => Err(error),
+                None => panic!(
+                    "Tried to read floating input {:?}, and its error type can't report it!",
+                    self.id
+                ),
+            },
+            FloatPolicy::Random => Ok(wiring.random_bool()),
+            FloatPolicy::Record => Ok(false),
+        }
+    }
+
+    fn floating_panic(&self) -> ! {
+        panic!(
+            "Tried to read floating input {:?}, value is non-deterministic!",
+            self.id
+        )
+    }
+
+    /// The level of the pin, taking the wiring into account.
+    fn state(&self) -> State {
+        match self.wiring {
+            Some(wiring) => wiring.level(self.id),
+            None => self.drive.state(),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! A mock SPI bus.
+
+use core::convert::Infallible;
+use embedded_hal::blocking::spi::{Transfer, Write};
+
+/// The largest number of bytes a [`MockSpi`] remembers.
+pub const SPI_CAPACITY: usize = 256;
+
+/// A mock SPI bus that records the bytes written to it, and answers
+/// transfers with scripted bytes.
+///
+/// Only the first [`SPI_CAPACITY`] bytes are kept, but all of them are
+/// counted. It isn't connected to a [`Wiring`](super::Wiring).
+///
+/// ```
+/// use keypad::embedded_hal::blocking::spi::{Transfer, Write};
+/// use keypad::mock_hal::MockSpi;
+///
+/// let mut spi = MockSpi::with_responses(&[0xAB, 0xCD]);
+/// spi.write(&[1, 2]).unwrap();
+/// let mut words = [3, 4, 5];
+/// assert_eq!(spi.transfer(&mut words).unwrap(), [0xAB, 0xCD, 0xAB]);
+/// assert_eq!(spi.written(), [1, 2, 3, 4, 5]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockSpi {
+    written: [u8; SPI_CAPACITY],
+    len: usize,
+    responses: &'static [u8],
+    next_response: usize,
+}
+
+impl MockSpi {
+    /// Create a bus that answers transfers with zeros.
+    pub fn new() -> Self {
+        Self::with_responses(&[])
+    }
+
+    /// Create a bus that answers transfers with these bytes, starting over
+    /// after the last one.
+    pub fn with_responses(responses: &'static [u8]) -> Self {
+        Self {
+            written: [0; SPI_CAPACITY],
+            len: 0,
+            responses,
+            next_response: 0,
+        }
+    }
+
+    /// Change the bytes used to answer transfers, starting with the first.
+    pub fn set_responses(&mut self, responses: &'static [u8]) {
+        self.responses = responses;
+        self.next_response = 0;
+    }
+
+    /// The bytes written so far, or the first [`SPI_CAPACITY`] of them.
+    pub fn written(&self) -> &[u8] {
+        &self.written[..self.len.min(SPI_CAPACITY)]
+    }
+
+    /// The number of bytes written so far, including any that didn't fit.
+    pub fn written_len(&self) -> usize {
+        self.len
+    }
+
+    /// Forget the bytes written so far.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn record(&mut self, word: u8) {
+        if self.len < SPI_CAPACITY {
+            self.written[self.len] = word;
+        }
+        self.len = self.len.saturating_add(1);
+    }
+
+    fn respond(&mut self) -> u8 {
+        if self.responses.is_empty() {
+            return 0;
+        }
+        let word = self.responses[self.next_response % self.responses.len()];
+        self.next_response = self.next_response.wrapping_add(1);
+        word
+    }
+}
+
+impl Default for MockSpi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write<u8> for MockSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        for &word in words {
+            self.record(word);
+        }
+        Ok(())
+    }
+}
+
+impl Transfer<u8> for MockSpi {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        for word in words.iter_mut() {
+            self.record(*word);
+            *word = self.respond();
+        }
+        Ok(words)
+    }
+}
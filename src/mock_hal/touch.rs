@@ -0,0 +1,144 @@
+//! A mock capacitive touch keypad controller.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A mock TTP229 16-key capacitive touch controller, in its two-wire serial
+/// mode with active-low outputs.
+///
+/// The controller's SCL input and SDO output are borrowed as pins with
+/// [`clock_pin()`](MockTtp229::clock_pin) and
+/// [`data_pin()`](MockTtp229::data_pin). Each falling edge of SCL puts the
+/// next key on SDO, starting with key 0, and SDO is low if that key is
+/// touched. After key 15, the next falling edge starts over with key 0.
+/// Before the first falling edge, SDO is high.
+///
+/// ```
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::MockTtp229;
+///
+/// let touch = MockTtp229::new();
+/// touch.touch(1);
+/// let mut scl = touch.clock_pin();
+/// let sdo = touch.data_pin();
+///
+/// let mut keys = Vec::new();
+/// for _ in 0..3 {
+///     scl.set_low().unwrap();
+///     keys.push(sdo.is_low().unwrap());
+///     scl.set_high().unwrap();
+/// }
+/// assert_eq!(keys, [false, true, false]);
+/// assert_eq!(touch.bits_clocked(), 3);
+/// ```
+#[derive(Debug)]
+pub struct MockTtp229 {
+    touched: Cell<u16>,
+    clock_high: Cell<bool>,
+    /// The number of falling edges since the last reset, 0 to 16.
+    edges: Cell<u8>,
+    clocked: Cell<usize>,
+}
+
+impl MockTtp229 {
+    /// Create a mock controller with no keys touched, and SCL high.
+    pub fn new() -> Self {
+        Self {
+            touched: Cell::new(0),
+            clock_high: Cell::new(true),
+            edges: Cell::new(0),
+            clocked: Cell::new(0),
+        }
+    }
+
+    /// Touch one of the keys 0 to 15.
+    pub fn touch(&self, key: u8) {
+        self.touched.set(self.touched.get() | 1 << key);
+    }
+
+    /// Stop touching one of the keys 0 to 15.
+    pub fn untouch(&self, key: u8) {
+        self.touched.set(self.touched.get() & !(1 << key));
+    }
+
+    /// The number of bits clocked out so far.
+    pub fn bits_clocked(&self) -> usize {
+        self.clocked.get()
+    }
+
+    /// Start over with key 0 at the next falling edge, like the real
+    /// controller does when SCL stays high for 2 ms.
+    pub fn reset(&self) {
+        self.edges.set(0);
+    }
+
+    /// The pin connected to the SCL input.
+    pub fn clock_pin(&self) -> Ttp229Clock<'_> {
+        Ttp229Clock { device: self }
+    }
+
+    /// The pin connected to the SDO output.
+    pub fn data_pin(&self) -> Ttp229Data<'_> {
+        Ttp229Data { device: self }
+    }
+
+    fn set_clock(&self, high: bool) {
+        if self.clock_high.get() && !high {
+            self.edges.set(self.edges.get() % 16 + 1);
+            self.clocked.set(self.clocked.get() + 1);
+        }
+        self.clock_high.set(high);
+    }
+
+    fn data_high(&self) -> bool {
+        match self.edges.get() {
+            0 => true,
+            edges => self.touched.get() & 1 << (edges - 1) == 0,
+        }
+    }
+}
+
+impl Default for MockTtp229 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The SCL input of a [`MockTtp229`].
+#[derive(Debug)]
+pub struct Ttp229Clock<'a> {
+    device: &'a MockTtp229,
+}
+
+impl<'a> OutputPin for Ttp229Clock<'a> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.device.set_clock(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.device.set_clock(true);
+        Ok(())
+    }
+}
+
+/// The SDO output of a [`MockTtp229`].
+#[derive(Debug)]
+pub struct Ttp229Data<'a> {
+    device: &'a MockTtp229,
+}
+
+impl<'a> InputPin for Ttp229Data<'a> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(self.device.data_high())
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.device.data_high())
+    }
+}
@@ -0,0 +1,250 @@
+//! Recording the operations performed on mock pins.
+
+use super::wiring::PinId;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// The largest number of operations a [`Trace`] can hold.
+pub const TRACE_CAPACITY: usize = 512;
+
+/// One kind of pin operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `OutputPin::set_low()`
+    SetLow,
+    /// `OutputPin::set_high()`
+    SetHigh,
+    /// `InputPin::is_low()`
+    IsLow,
+    /// `InputPin::is_high()`
+    IsHigh,
+}
+
+impl Operation {
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0b11 {
+            0 => Operation::SetLow,
+            1 => Operation::SetHigh,
+            2 => Operation::IsLow,
+            _ => Operation::IsHigh,
+        }
+    }
+
+    fn to_bits(self) -> u32 {
+        match self {
+            Operation::SetLow => 0,
+            Operation::SetHigh => 1,
+            Operation::IsLow => 2,
+            Operation::IsHigh => 3,
+        }
+    }
+
+    /// Is this an output operation, rather than a read?
+    pub fn is_output(&self) -> bool {
+        matches!(self, Operation::SetLow | Operation::SetHigh)
+    }
+}
+
+/// One recorded pin operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceEntry {
+    /// The position of this operation in the trace, starting from 0.
+    pub sequence: u32,
+    /// The pin that was used.
+    pub pin: PinId,
+    /// What was done with it.
+    pub operation: Operation,
+}
+
+/// A recording of the operations performed on the pins of a
+/// [`Wiring`](super::Wiring), in the order they happened.
+///
+/// Recording is off until [`start()`](Trace::start) is called. Only the first
+/// [`TRACE_CAPACITY`] operations are kept, but all of them are counted.
+/// Pins that aren't wired aren't recorded.
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use core::convert::Infallible;
+/// use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Operation, Output, PullUp};
+/// use keypad::mock_hal::{Wiring, GPIOA};
+///
+/// keypad_struct! {
+///     struct Keypad<Error = Infallible> {
+///         rows: (
+///             mock_hal::gpioa::PA0<Input<PullUp>>,
+///             mock_hal::gpioa::PA1<Input<PullUp>>,
+///         ),
+///         columns: (
+///             mock_hal::gpioa::PA2<Output<OpenDrain>>,
+///             mock_hal::gpioa::PA3<Output<OpenDrain>>,
+///         ),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// fn main() {
+///     let pins = GPIOA::split_wired(&WIRING);
+///     let (r0, r1, c0, c1) = (pins.pa0.id(), pins.pa1.id(), pins.pa2.id(), pins.pa3.id());
+///     let keypad = keypad_new!(Keypad {
+///         rows: (pins.pa0.into_pull_up_input(), pins.pa1.into_pull_up_input()),
+///         columns: (
+///             pins.pa2.into_open_drain_output(),
+///             pins.pa3.into_open_drain_output(),
+///         ),
+///     });
+///
+///     let trace = WIRING.trace();
+///     trace.start();
+///     keypad.scan().unwrap();
+///
+///     // Each key is read by selecting its column, reading its row, and
+///     // releasing the column again.
+///     trace.assert_operations(&[
+///         (c0, Operation::SetLow), (r0, Operation::IsLow), (c0, Operation::SetHigh),
+///         (c1, Operation::SetLow), (r0, Operation::IsLow), (c1, Operation::SetHigh),
+///         (c0, Operation::SetLow), (r1, Operation::IsLow), (c0, Operation::SetHigh),
+///         (c1, Operation::SetLow), (r1, Operation::IsLow), (c1, Operation::SetHigh),
+///     ]);
+///     assert_eq!(trace.count(Operation::IsLow), 4);
+///     assert_eq!(trace.last_output(c0), Some(Operation::SetHigh));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Trace {
+    recording: AtomicBool,
+    len: AtomicU32,
+    /// Each entry is the operation in bits 0-1, and the pin's port and pin
+    /// number above that.
+    entries: [AtomicU32; TRACE_CAPACITY],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicU32 = AtomicU32::new(0);
+
+impl Trace {
+    pub(super) const fn new() -> Self {
+        Self {
+            recording: AtomicBool::new(false),
+            len: AtomicU32::new(0),
+            entries: [EMPTY; TRACE_CAPACITY],
+        }
+    }
+
+    /// Forget any recorded operations, and start recording.
+    pub fn start(&self) {
+        self.clear();
+        self.recording.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop recording, keeping the operations recorded so far.
+    pub fn stop(&self) {
+        self.recording.store(false, Ordering::Relaxed);
+    }
+
+    /// Forget any recorded operations, without starting or stopping.
+    pub fn clear(&self) {
+        self.len.store(0, Ordering::Relaxed);
+    }
+
+    /// The number of operations recorded since the trace was last cleared,
+    /// including any that didn't fit.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed) as usize
+    }
+
+    /// Has nothing been recorded since the trace was last cleared?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Did more operations happen than the trace could hold?
+    pub fn overflowed(&self) -> bool {
+        self.len() > TRACE_CAPACITY
+    }
+
+    /// Iterate over the recorded operations, in order.
+    pub fn entries(&self) -> impl Iterator<Item = TraceEntry> + '_ {
+        (0..self.len().min(TRACE_CAPACITY)).map(move |i| {
+            let bits = self.entries[i].load(Ordering::Relaxed);
+            TraceEntry {
+                sequence: i as u32,
+                pin: PinId::new((bits >> 10) as u8, ((bits >> 2) & 0xFF) as u8),
+                operation: Operation::from_bits(bits),
+            }
+        })
+    }
+
+    /// The number of recorded operations of this kind, on any pin.
+    pub fn count(&self, operation: Operation) -> usize {
+        self.entries()
+            .filter(|entry| entry.operation == operation)
+            .count()
+    }
+
+    /// The number of recorded operations of this kind on one pin.
+    pub fn count_for(&self, pin: PinId, operation: Operation) -> usize {
+        self.entries()
+            .filter(|entry| entry.pin == pin && entry.operation == operation)
+            .count()
+    }
+
+    /// The last `SetLow` or `SetHigh` recorded for this pin, if any.
+    pub fn last_output(&self, pin: PinId) -> Option<Operation> {
+        self.entries()
+            .filter(|entry| entry.pin == pin && entry.operation.is_output())
+            .last()
+            .map(|entry| entry.operation)
+    }
+
+    /// Check that exactly these operations were recorded, in this order.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of the first difference if they weren't.
+    pub fn assert_operations(&self, expected: &[(PinId, Operation)]) {
+        assert!(!self.overflowed(), "the trace overflowed");
+        let mut entries = self.entries();
+        for (i, &(pin, operation)) in expected.iter().enumerate() {
+            match entries.next() {
+                Some(entry) => assert!(
+                    entry.pin == pin && entry.operation == operation,
+                    "operation {} was {:?} on {:?}, expected {:?} on {:?}",
+                    i,
+                    entry.operation,
+                    entry.pin,
+                    operation,
+                    pin
+                ),
+                None => panic!(
+                    "only {} operations were recorded, expected {:?} on {:?} next",
+                    i, operation, pin
+                ),
+            }
+        }
+        if let Some(entry) = entries.next() {
+            panic!(
+                "unexpected operation {}: {:?} on {:?}",
+                entry.sequence, entry.operation, entry.pin
+            );
+        }
+    }
+
+    /// Record an operation, if recording.
+    pub(super) fn record(&self, pin: PinId, operation: Operation) {
+        if !self.recording.load(Ordering::Relaxed) {
+            return;
+        }
+        let len = self.len();
+        if len < TRACE_CAPACITY {
+            let bits =
+                u32::from(pin.port()) << 10 | u32::from(pin.pin()) << 2 | operation.to_bits();
+            self.entries[len].store(bits, Ordering::Relaxed);
+        }
+        self.len
+            .store(len.saturating_add(1) as u32, Ordering::Relaxed);
+    }
+}
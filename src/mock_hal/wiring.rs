@@ -0,0 +1,756 @@
+//! Simulated wires and switches connecting mock pins.
+
+use super::clock::{MockClock, MockDelay};
+use super::fault::FloatPolicy;
+use super::noise::{Noise, Rng};
+use super::trace::Trace;
+use super::State;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+/// The largest number of mock ports that can share one `Wiring`.
+pub const MAX_PORTS: usize = 8;
+
+/// The number of pins in each mock port.
+pub const PINS_PER_PORT: usize = 16;
+
+const MAX_PINS: usize = MAX_PORTS * PINS_PER_PORT;
+const SWITCH_WORDS: usize = MAX_PINS * MAX_PINS / 32;
+const WORDS_PER_PIN: usize = MAX_PINS / 32;
+
+/// How many switches can be bouncing at the same time.
+const BOUNCE_SLOTS: usize = 16;
+
+/// Identifies one mock pin by its port and pin number.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PinId {
+    port: u8,
+    pin: u8,
+}
+
+impl PinId {
+    /// Create a new `PinId`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port or pin number is too large.
+    pub fn new(port: u8, pin: u8) -> Self {
+        assert!(
+            usize::from(port) < MAX_PORTS && usize::from(pin) < PINS_PER_PORT,
+            "mock pin number out of range"
+        );
+        Self { port, pin }
+    }
+
+    /// The port number, where port A is 0.
+    pub const fn port(&self) -> u8 {
+        self.port
+    }
+
+    /// The pin number within the port.
+    pub const fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    fn index(&self) -> usize {
+        usize::from(self.port) * PINS_PER_PORT + usize::from(self.pin)
+    }
+
+    fn from_index(index: usize) -> Self {
+        Self {
+            port: (index / PINS_PER_PORT) as u8,
+            pin: (index % PINS_PER_PORT) as u8,
+        }
+    }
+}
+
+impl fmt::Debug for PinId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "P{}{}", (b'A' + self.port) as char, self.pin)
+    }
+}
+
+/// What a mock pin does to the wire it's connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[doc(hidden)]
+pub enum Drive {
+    /// Nothing: a floating input, or an open-drain output that's set high.
+    Float = 0,
+    /// An output that's set low.
+    Low = 1,
+    /// A push-pull output that's set high.
+    High = 2,
+    /// An input with a pull-up resistor.
+    PullUp = 3,
+    /// An input with a pull-down resistor.
+    PullDown = 4,
+}
+
+impl Drive {
+    /// The level of a wire that's only connected to a pin with this drive.
+    pub(super) fn state(self) -> State {
+        match self {
+            Drive::Low | Drive::PullDown => State::Low,
+            Drive::High | Drive::PullUp => State::High,
+            Drive::Float => State::Float,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Drive::Low,
+            2 => Drive::High,
+            3 => Drive::PullUp,
+            4 => Drive::PullDown,
+            _ => Drive::Float,
+        }
+    }
+}
+
+/// A simulated circuit board that connects mock pins to each other.
+///
+/// Pins created with [`GpioExt::split_wired()`](super::GpioExt::split_wired)
+/// tell the `Wiring` how they're being driven, and read their level from it.
+/// Closing a switch between two pins connects their wires, just like pressing
+/// a key in a keypad matrix connects its row and column. The level of a group
+/// of connected pins is:
+///
+/// - low if any of them is an output set low,
+/// - otherwise high if any of them is a push-pull output set high,
+/// - otherwise high if any of them is an input with a pull-up resistor, and
+///   none has a pull-down resistor,
+/// - otherwise low if any of them is an input with a pull-down resistor, and
+///   none has a pull-up resistor,
+/// - otherwise floating.
+///
+/// A real circuit would be shorted if one push-pull output was set high and
+/// another was set low. The mock doesn't check for that.
+///
+/// Every operation on the wired pins can be recorded with its
+/// [`trace()`](Self::trace).
+///
+/// Switches and wires are perfect by default. Use [`set_noise()`](Self::set_noise)
+/// to make them bounce and glitch like real ones.
+///
+/// A `Wiring` is meant to be stored in a `static`, so that every pin can refer
+/// to it. Use one `Wiring` per test, because tests may run in parallel.
+/// [`MockMatrix`] is a convenient way to press and release the keys of a
+/// keypad matrix.
+///
+/// ```
+/// use keypad::embedded_hal::digital::v2::{InputPin, OutputPin};
+/// use keypad::mock_hal::{GpioExt, Wiring, GPIOA};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let row = pins.pa0.into_pull_up_input();
+/// let mut column = pins.pa1.into_open_drain_output();
+///
+/// column.set_low().unwrap();
+/// assert!(row.is_high().unwrap());
+///
+/// WIRING.connect(row.id(), column.id());
+/// assert!(row.is_low().unwrap());
+///
+/// column.set_high().unwrap();
+/// assert!(row.is_high().unwrap());
+/// ```
+///
+/// New output pins start out driven the way their [`OutputMode`](super::OutputMode)
+/// says, so an open-drain column doesn't pull its rows low until it's set
+/// low.
+///
+/// ```
+/// use keypad::embedded_hal::digital::v2::InputPin;
+/// use keypad::mock_hal::{GpioExt, Wiring, GPIOA};
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// let pins = GPIOA::split_wired(&WIRING);
+/// let row = pins.pa0.into_pull_up_input();
+/// let open_drain = pins.pa1.into_open_drain_output();
+/// let push_pull = pins.pa2.into_push_pull_output();
+///
+/// WIRING.connect(row.id(), open_drain.id());
+/// assert!(row.is_high().unwrap());
+/// WIRING.connect(row.id(), push_pull.id());
+/// assert!(row.is_low().unwrap());
+/// ```
+pub struct Wiring {
+    drives: [AtomicU8; MAX_PINS],
+    /// One bit for each pair of pins, set if there's a closed switch between
+    /// them. The bits are always set in both directions.
+    switches: [AtomicU32; SWITCH_WORDS],
+
+    /// The number of reads so far, used as the simulation's clock.
+    reads: AtomicU32,
+    /// The virtual time in nanoseconds, split into two words so that it
+    /// doesn't need 64-bit atomics.
+    time_high: AtomicU32,
+    time_low: AtomicU32,
+    rng: Rng,
+    seed: AtomicU32,
+    bounce_reads: AtomicU32,
+    glitch_chance: AtomicU32,
+    settle_reads: AtomicU32,
+    bounce_us: AtomicU32,
+    settle_us: AtomicU32,
+    /// How each pin was driven before its last change, for settling.
+    previous_drives: [AtomicU8; MAX_PINS],
+    /// The read count when each pin's drive last changed.
+    drive_changed: [AtomicU32; MAX_PINS],
+    /// The virtual time in microseconds when each pin's drive last changed.
+    drive_changed_us: [AtomicU32; MAX_PINS],
+    /// Recently changed switches, as `(a << 8 | b) + 1` with `a < b`, or 0
+    /// for an empty slot.
+    bounce_pairs: [AtomicU32; BOUNCE_SLOTS],
+    /// The read count when each recently changed switch changed.
+    bounce_started: [AtomicU32; BOUNCE_SLOTS],
+    /// The virtual time in microseconds when each recently changed switch
+    /// changed.
+    bounce_started_us: [AtomicU32; BOUNCE_SLOTS],
+    next_bounce_slot: AtomicU32,
+
+    trace: Trace,
+
+    float_policies: [AtomicU8; MAX_PORTS],
+    floating_reads: AtomicU32,
+    /// The index of the last pin read while floating, plus 1, or 0 for none.
+    last_floating: AtomicU32,
+}
+
+// Atomics are only used so that a `Wiring` can be stored in a `static`. Only
+// loads and stores are used, so that the mock still compiles for targets
+// without atomic read-modify-write instructions.
+#[allow(clippy::declare_interior_mutable_const)]
+const FLOATING: AtomicU8 = AtomicU8::new(Drive::Float as u8);
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU32 = AtomicU32::new(0);
+
+impl Wiring {
+    /// Create a new `Wiring` with every pin floating and every switch open.
+    pub const fn new() -> Self {
+        Self {
+            drives: [FLOATING; MAX_PINS],
+            switches: [ZERO; SWITCH_WORDS],
+            reads: AtomicU32::new(0),
+            time_high: AtomicU32::new(0),
+            time_low: AtomicU32::new(0),
+            rng: Rng::new(),
+            seed: AtomicU32::new(0),
+            bounce_reads: AtomicU32::new(0),
+            glitch_chance: AtomicU32::new(0),
+            settle_reads: AtomicU32::new(0),
+            bounce_us: AtomicU32::new(0),
+            settle_us: AtomicU32::new(0),
+            previous_drives: [FLOATING; MAX_PINS],
+            drive_changed: [ZERO; MAX_PINS],
+            drive_changed_us: [ZERO; MAX_PINS],
+            bounce_pairs: [ZERO; BOUNCE_SLOTS],
+            bounce_started: [ZERO; BOUNCE_SLOTS],
+            bounce_started_us: [ZERO; BOUNCE_SLOTS],
+            next_bounce_slot: AtomicU32::new(0),
+            trace: Trace::new(),
+            float_policies: [FLOATING; MAX_PORTS],
+            floating_reads: AtomicU32::new(0),
+            last_floating: AtomicU32::new(0),
+        }
+    }
+
+    /// Change the noise settings, and restart the random number generator
+    /// from the new seed.
+    pub fn set_noise(&self, noise: Noise) {
+        self.seed.store(noise.seed, Ordering::Relaxed);
+        self.rng.seed(noise.seed);
+        self.bounce_reads
+            .store(noise.bounce_reads, Ordering::Relaxed);
+        self.glitch_chance
+            .store(u32::from(noise.glitch_chance), Ordering::Relaxed);
+        self.settle_reads
+            .store(noise.settle_reads, Ordering::Relaxed);
+        self.bounce_us.store(noise.bounce_us, Ordering::Relaxed);
+        self.settle_us.store(noise.settle_us, Ordering::Relaxed);
+    }
+
+    /// The current noise settings.
+    pub fn noise(&self) -> Noise {
+        Noise {
+            seed: self.seed.load(Ordering::Relaxed),
+            bounce_reads: self.bounce_reads.load(Ordering::Relaxed),
+            glitch_chance: self.glitch_chance.load(Ordering::Relaxed) as u16,
+            settle_reads: self.settle_reads.load(Ordering::Relaxed),
+            bounce_us: self.bounce_us.load(Ordering::Relaxed),
+            settle_us: self.settle_us.load(Ordering::Relaxed),
+        }
+    }
+
+    /// A clock that reads this wiring's virtual time.
+    pub fn clock(&'static self) -> MockClock {
+        MockClock::new(self)
+    }
+
+    /// A delay provider that advances this wiring's virtual time.
+    pub fn delay(&'static self) -> MockDelay {
+        MockDelay::new(self)
+    }
+
+    /// The virtual time in nanoseconds.
+    pub(super) fn time_ns(&self) -> u64 {
+        u64::from(self.time_high.load(Ordering::Relaxed)) << 32
+            | u64::from(self.time_low.load(Ordering::Relaxed))
+    }
+
+    /// Move the virtual time forward.
+    pub(super) fn advance_ns(&self, ns: u64) {
+        let time = self.time_ns().saturating_add(ns);
+        self.time_high.store((time >> 32) as u32, Ordering::Relaxed);
+        self.time_low.store(time as u32, Ordering::Relaxed);
+    }
+
+    /// The virtual time in microseconds, wrapped to 32 bits.
+    fn time_us(&self) -> u32 {
+        (self.time_ns() / 1_000) as u32
+    }
+
+    /// The recording of operations on the wired pins.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// The number of times a wired input pin has been read.
+    pub fn reads(&self) -> u32 {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    /// Choose what the wired input pins of one port do when they're read
+    /// while floating.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port number is too large.
+    pub fn set_float_policy(&self, port: u8, policy: FloatPolicy) {
+        assert!(
+            usize::from(port) < MAX_PORTS,
+            "mock port number out of range"
+        );
+        self.float_policies[usize::from(port)].store(policy.to_u8(), Ordering::Relaxed);
+    }
+
+    /// What the wired input pins of one port do when they're read while
+    /// floating.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port number is too large.
+    pub fn float_policy(&self, port: u8) -> FloatPolicy {
+        FloatPolicy::from_u8(self.float_policies[usize::from(port)].load(Ordering::Relaxed))
+    }
+
+    /// The number of times a wired input pin was read while floating, and
+    /// didn't panic.
+    pub fn floating_reads(&self) -> u32 {
+        self.floating_reads.load(Ordering::Relaxed)
+    }
+
+    /// The last pin that was read while floating, if any.
+    pub fn last_floating_read(&self) -> Option<PinId> {
+        match self.last_floating.load(Ordering::Relaxed) {
+            0 => None,
+            index => Some(PinId::from_index(index as usize - 1)),
+        }
+    }
+
+    /// Forget about any floating reads so far.
+    pub fn clear_floating_reads(&self) {
+        self.floating_reads.store(0, Ordering::Relaxed);
+        self.last_floating.store(0, Ordering::Relaxed);
+    }
+
+    /// Record that a pin was read while floating, and return the policy for
+    /// its port.
+    pub(super) fn read_floating(&self, pin: PinId) -> FloatPolicy {
+        let policy = self.float_policy(pin.port());
+        if policy != FloatPolicy::Panic {
+            self.floating_reads
+                .store(self.floating_reads().wrapping_add(1), Ordering::Relaxed);
+            self.last_floating
+                .store(pin.index() as u32 + 1, Ordering::Relaxed);
+        }
+        policy
+    }
+
+    /// A pseudo-random level.
+    pub(super) fn random_bool(&self) -> bool {
+        self.rng.next_u32() & 1 != 0
+    }
+
+    /// Close the switch between two pins, connecting them.
+    pub fn connect(&self, a: PinId, b: PinId) {
+        self.set_switch(a, b, true);
+        self.set_switch(b, a, true);
+    }
+
+    /// Open the switch between two pins, disconnecting them.
+    pub fn disconnect(&self, a: PinId, b: PinId) {
+        self.set_switch(a, b, false);
+        self.set_switch(b, a, false);
+    }
+
+    /// Open or close the switch between two pins.
+    pub fn set_connected(&self, a: PinId, b: PinId, connected: bool) {
+        if connected {
+            self.connect(a, b);
+        } else {
+            self.disconnect(a, b);
+        }
+    }
+
+    /// Is the switch between these two pins closed?
+    pub fn is_connected(&self, a: PinId, b: PinId) -> bool {
+        self.is_closed(a.index(), b.index())
+    }
+
+    /// Open every switch, without bouncing.
+    pub fn disconnect_all(&self) {
+        for word in self.switches.iter() {
+            word.store(0, Ordering::Relaxed);
+        }
+        for pair in self.bounce_pairs.iter() {
+            pair.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Create a `MockMatrix` for pressing the keys of a keypad matrix with the
+    /// given row and column pins.
+    pub fn matrix<const ROWS: usize, const COLUMNS: usize>(
+        &'static self,
+        rows: [PinId; ROWS],
+        columns: [PinId; COLUMNS],
+    ) -> MockMatrix<ROWS, COLUMNS> {
+        MockMatrix {
+            wiring: self,
+            rows,
+            columns,
+        }
+    }
+
+    /// Record how a pin is driving its wire.
+    pub(super) fn drive(&self, pin: PinId, drive: Drive) {
+        let index = pin.index();
+        let old = self.drives[index].load(Ordering::Relaxed);
+        if old != drive as u8 {
+            self.previous_drives[index].store(old, Ordering::Relaxed);
+            self.drive_changed[index].store(self.reads(), Ordering::Relaxed);
+            self.drive_changed_us[index].store(self.time_us(), Ordering::Relaxed);
+            self.drives[index].store(drive as u8, Ordering::Relaxed);
+        }
+    }
+
+    /// Find the level of the wire connected to this pin, following closed
+    /// switches, and move the simulation forward by one read.
+    pub(super) fn level(&self, pin: PinId) -> State {
+        let now = self.reads();
+        self.reads.store(now.wrapping_add(1), Ordering::Relaxed);
+
+        let mut visited = [false; MAX_PINS];
+        let mut stack = [0; MAX_PINS];
+        let mut stack_len = 1;
+        stack[0] = pin.index();
+        visited[pin.index()] = true;
+
+        let mut strongest = Drive::Float;
+        let (mut pulled_up, mut pulled_down) = (false, false);
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            match self.drive_at(index, now) {
+                Drive::PullUp => pulled_up = true,
+                Drive::PullDown => pulled_down = true,
+                drive => strongest = Self::stronger(strongest, drive),
+            }
+
+            // The switches of one pin are stored in consecutive words.
+            for w in 0..WORDS_PER_PIN {
+                let mut bits = self.switches[index * WORDS_PER_PIN + w].load(Ordering::Relaxed);
+                while bits != 0 {
+                    let other = w * 32 + bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    if !visited[other] && self.survives_bounce(index, other, now) {
+                        visited[other] = true;
+                        stack[stack_len] = other;
+                        stack_len += 1;
+                    }
+                }
+            }
+            // Open switches that are bouncing may be closed for this read.
+            for slot in 0..BOUNCE_SLOTS {
+                if let Some(other) = self.bouncing_partner(slot, index, now) {
+                    if !visited[other]
+                        && !self.is_closed(index, other)
+                        && self.rng.next_u32() & 1 != 0
+                    {
+                        visited[other] = true;
+                        stack[stack_len] = other;
+                        stack_len += 1;
+                    }
+                }
+            }
+        }
+
+        let state = match (strongest, pulled_up, pulled_down) {
+            (Drive::Float, true, false) => State::High,
+            (Drive::Float, false, true) => State::Low,
+            (drive, _, _) => drive.state(),
+        };
+        match state {
+            State::High if self.glitch() => State::Low,
+            State::Low if self.glitch() => State::High,
+            state => state,
+        }
+    }
+
+    fn is_closed(&self, a: usize, b: usize) -> bool {
+        let word = self.switches[a * WORDS_PER_PIN + b / 32].load(Ordering::Relaxed);
+        word & (1 << (b % 32)) != 0
+    }
+
+    /// Does a closed switch stay closed for this read? It may be open if it's
+    /// bouncing.
+    fn survives_bounce(&self, a: usize, b: usize, now: u32) -> bool {
+        let pair = Self::bounce_pair(a, b);
+        let bouncing = (0..BOUNCE_SLOTS).any(|slot| {
+            self.bounce_pairs[slot].load(Ordering::Relaxed) == pair
+                && self.is_bounce_active(slot, now)
+        });
+        !bouncing || self.rng.next_u32() & 1 != 0
+    }
+
+    /// If this bounce slot holds a bouncing switch connected to pin `index`,
+    /// return the pin on its other side.
+    fn bouncing_partner(&self, slot: usize, index: usize, now: u32) -> Option<usize> {
+        let pair = self.bounce_pairs[slot].load(Ordering::Relaxed);
+        if pair == 0 || !self.is_bounce_active(slot, now) {
+            return None;
+        }
+        let (a, b) = (((pair - 1) >> 8) as usize, ((pair - 1) & 0xFF) as usize);
+        if a == index {
+            Some(b)
+        } else if b == index {
+            Some(a)
+        } else {
+            None
+        }
+    }
+
+    fn is_bounce_active(&self, slot: usize, now: u32) -> bool {
+        let started = self.bounce_started[slot].load(Ordering::Relaxed);
+        let started_us = self.bounce_started_us[slot].load(Ordering::Relaxed);
+        now.wrapping_sub(started) < self.bounce_reads.load(Ordering::Relaxed)
+            || self.time_us().wrapping_sub(started_us) < self.bounce_us.load(Ordering::Relaxed)
+    }
+
+    fn bounce_pair(a: usize, b: usize) -> u32 {
+        let (low, high) = if a < b { (a, b) } else { (b, a) };
+        ((low << 8 | high) + 1) as u32
+    }
+
+    /// Start bouncing a switch that just changed.
+    fn start_bounce(&self, a: PinId, b: PinId) {
+        if self.bounce_reads.load(Ordering::Relaxed) == 0
+            && self.bounce_us.load(Ordering::Relaxed) == 0
+        {
+            return;
+        }
+        let pair = Self::bounce_pair(a.index(), b.index());
+        let slot = self
+            .bounce_pairs
+            .iter()
+            .position(|slot| slot.load(Ordering::Relaxed) == pair)
+            .unwrap_or_else(|| {
+                let next = self.next_bounce_slot.load(Ordering::Relaxed) as usize;
+                self.next_bounce_slot
+                    .store(((next + 1) % BOUNCE_SLOTS) as u32, Ordering::Relaxed);
+                next
+            });
+        self.bounce_pairs[slot].store(pair, Ordering::Relaxed);
+        self.bounce_started[slot].store(self.reads(), Ordering::Relaxed);
+        self.bounce_started_us[slot].store(self.time_us(), Ordering::Relaxed);
+    }
+
+    /// Should this read be flipped by a glitch?
+    fn glitch(&self) -> bool {
+        self.rng.chance(self.glitch_chance.load(Ordering::Relaxed))
+    }
+
+    /// How a pin is driving its wire during this read, taking settling into
+    /// account.
+    fn drive_at(&self, index: usize, now: u32) -> Drive {
+        let changed = self.drive_changed[index].load(Ordering::Relaxed);
+        let changed_us = self.drive_changed_us[index].load(Ordering::Relaxed);
+        let settling = now.wrapping_sub(changed) < self.settle_reads.load(Ordering::Relaxed)
+            || self.time_us().wrapping_sub(changed_us) < self.settle_us.load(Ordering::Relaxed);
+        let drives = if settling {
+            &self.previous_drives
+        } else {
+            &self.drives
+        };
+        Drive::from_u8(drives[index].load(Ordering::Relaxed))
+    }
+
+    /// Pick the drive that wins when two pins are connected.
+    fn stronger(a: Drive, b: Drive) -> Drive {
+        let rank = |drive| match drive {
+            Drive::Low => 2,
+            Drive::High => 1,
+            _ => 0,
+        };
+        if rank(b) > rank(a) {
+            b
+        } else {
+            a
+        }
+    }
+
+    fn switch_bit(a: PinId, b: PinId) -> (usize, u32) {
+        let bit_index = a.index() * MAX_PINS + b.index();
+        (bit_index / 32, 1 << (bit_index % 32))
+    }
+
+    fn set_switch(&self, a: PinId, b: PinId, closed: bool) {
+        let (word, bit) = Self::switch_bit(a, b);
+        let old = self.switches[word].load(Ordering::Relaxed);
+        let new = if closed { old | bit } else { old & !bit };
+        if new != old {
+            self.switches[word].store(new, Ordering::Relaxed);
+            self.start_bounce(a, b);
+        }
+    }
+}
+
+impl Default for Wiring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Wiring {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Wiring").finish_non_exhaustive()
+    }
+}
+
+/// The keys of a simulated keypad matrix, which can be pressed and released
+/// from a test.
+///
+/// Pressing the key at `(row, column)` closes the switch between that row's pin
+/// and that column's pin. There are no diodes, so pressing several keys can
+/// cause ghosting just like in a real matrix without diodes.
+///
+/// Create one with [`Wiring::matrix()`].
+///
+/// ```
+/// # #![cfg_attr(docs_rs_workaround, feature(macro_vis_matcher))]
+/// #[macro_use]
+/// extern crate keypad;
+///
+/// use core::convert::Infallible;
+/// use keypad::mock_hal::{self, GpioExt, Input, OpenDrain, Output, PullUp, Wiring, GPIOA};
+/// use keypad::Position;
+///
+/// keypad_struct! {
+///     struct Keypad<Error = Infallible> {
+///         rows: (
+///             mock_hal::gpioa::PA0<Input<PullUp>>,
+///             mock_hal::gpioa::PA1<Input<PullUp>>,
+///         ),
+///         columns: (
+///             mock_hal::gpioa::PA2<Output<OpenDrain>>,
+///             mock_hal::gpioa::PA3<Output<OpenDrain>>,
+///             mock_hal::gpioa::PA4<Output<OpenDrain>>,
+///         ),
+///     }
+/// }
+///
+/// static WIRING: Wiring = Wiring::new();
+///
+/// fn main() {
+///     let pins = GPIOA::split_wired(&WIRING);
+///     let matrix = WIRING.matrix(
+///         [pins.pa0.id(), pins.pa1.id()],
+///         [pins.pa2.id(), pins.pa3.id(), pins.pa4.id()],
+///     );
+///
+///     let keypad = keypad_new!(Keypad {
+///         rows: (
+///             pins.pa0.into_pull_up_input(),
+///             pins.pa1.into_pull_up_input(),
+///         ),
+///         columns: (
+///             pins.pa2.into_open_drain_output(),
+///             pins.pa3.into_open_drain_output(),
+///             pins.pa4.into_open_drain_output(),
+///         ),
+///     });
+///
+///     matrix.press(1, 2);
+///     let state = keypad.scan().unwrap();
+///     assert!(state.is_pressed(Position::new(1, 2)));
+///     assert_eq!(state.count(), 1);
+///
+///     matrix.release(1, 2);
+///     assert!(!keypad.scan().unwrap().any());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MockMatrix<const ROWS: usize, const COLUMNS: usize> {
+    wiring: &'static Wiring,
+    rows: [PinId; ROWS],
+    columns: [PinId; COLUMNS],
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> MockMatrix<ROWS, COLUMNS> {
+    /// The wiring that the keys are part of.
+    pub fn wiring(&self) -> &'static Wiring {
+        self.wiring
+    }
+
+    /// Press the key at this row and column.
+    pub fn press(&self, row: usize, column: usize) {
+        self.wiring.connect(self.rows[row], self.columns[column]);
+    }
+
+    /// Release the key at this row and column.
+    pub fn release(&self, row: usize, column: usize) {
+        self.wiring.disconnect(self.rows[row], self.columns[column]);
+    }
+
+    /// Is the key at this row and column pressed?
+    pub fn is_pressed(&self, row: usize, column: usize) -> bool {
+        self.wiring
+            .is_connected(self.rows[row], self.columns[column])
+    }
+
+    /// Release every key.
+    pub fn release_all(&self) {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                self.release(row, column);
+            }
+        }
+    }
+
+    /// Press exactly the keys that are pressed in this `KeyState`, and release
+    /// the others.
+    pub fn set_state(&self, state: &crate::KeyState<ROWS, COLUMNS>) {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                if state.is_pressed(crate::Position::new(row, column)) {
+                    self.press(row, column);
+                } else {
+                    self.release(row, column);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Decoding Morse code keyed on a single key.
+//!
+//! [`MorseDecoder`] measures how long a key is held and released, and turns
+//! those durations into characters. It doesn't need a fixed keying speed:
+//! every dot and dash it hears updates its estimate of the length of one Morse
+//! "unit", so it follows the operator as they speed up or slow down.
+//!
+//! With a unit length of `u`:
+//!
+//! - a press shorter than `2u` is a dot, and a longer one is a dash,
+//! - a release longer than `2u` ends the current letter, and
+//! - a release longer than `5u` also ends the current word.
+//!
+//! The decoder doesn't have a clock of its own. Call
+//! [`update()`](MorseDecoder::update) regularly with the current time in
+//! milliseconds and whether the key is pressed, or use
+//! [`poll()`](MorseDecoder::poll) to read the key directly. It must keep being
+//! called while the key is released, so that it can notice the gaps between
+//! letters and words.
+//!
+//! ```
+//! use keypad::morse::MorseDecoder;
+//!
+//! let mut decoder = MorseDecoder::new(100);
+//! let mut text = String::new();
+//!
+//! // Key "SOS" at a unit length of 100 ms, sampling the key every 10 ms.
+//! let mut pressed = Vec::new();
+//! for letter in ["...", "---", "..."].iter() {
+//!     for symbol in letter.chars() {
+//!         let units = if symbol == '.' { 1 } else { 3 };
+//!         pressed.extend(std::iter::repeat(true).take(units * 10));
+//!         pressed.extend(std::iter::repeat(false).take(10));
+//!     }
+//!     pressed.extend(std::iter::repeat(false).take(20));
+//! }
+//! pressed.extend(std::iter::repeat(false).take(60));
+//!
+//! for (i, &is_pressed) in pressed.iter().enumerate() {
+//!     if let Some(c) = decoder.update(is_pressed, i as u32 * 10) {
+//!         text.push(c);
+//!     }
+//! }
+//! assert_eq!(text, "SOS ");
+//! ```
+
+use embedded_hal::digital::v2::InputPin;
+
+/// The longest letter the decoder will collect. Anything longer is decoded as
+/// [`MorseDecoder::UNKNOWN`].
+const MAX_SYMBOLS: u8 = 7;
+
+/// Letters, digits and common punctuation in International Morse Code.
+const TABLE: [(&str, char); 54] = [
+    (".-", 'A'),
+    ("-...", 'B'),
+    ("-.-.", 'C'),
+    ("-..", 'D'),
+    (".", 'E'),
+    ("..-.", 'F'),
+    ("--.", 'G'),
+    ("....", 'H'),
+    ("..", 'I'),
+    (".---", 'J'),
+    ("-.-", 'K'),
+    (".-..", 'L'),
+    ("--", 'M'),
+    ("-.", 'N'),
+    ("---", 'O'),
+    (".--.", 'P'),
+    ("--.-", 'Q'),
+    (".-.", 'R'),
+    ("...", 'S'),
+    ("-", 'T'),
+    ("..-", 'U'),
+    ("...-", 'V'),
+    (".--", 'W'),
+    ("-..-", 'X'),
+    ("-.--", 'Y'),
+    ("--..", 'Z'),
+    ("-----", '0'),
+    (".----", '1'),
+    ("..---", '2'),
+    ("...--", '3'),
+    ("....-", '4'),
+    (".....", '5'),
+    ("-....", '6'),
+    ("--...", '7'),
+    ("---..", '8'),
+    ("----.", '9'),
+    (".-.-.-", '.'),
+    ("--..--", ','),
+    ("..--..", '?'),
+    (".----.", '\''),
+    ("-.-.--", '!'),
+    ("-..-.", '/'),
+    ("-.--.", '('),
+    ("-.--.-", ')'),
+    (".-...", '&'),
+    ("---...", ':'),
+    ("-.-.-.", ';'),
+    ("-...-", '='),
+    (".-.-.", '+'),
+    ("-....-", '-'),
+    ("..--.-", '_'),
+    (".-..-.", '"'),
+    ("...-..-", '$'),
+    (".--.-.", '@'),
+];
+
+/// The dots and dashes of one letter. Bit `n` is set if the `n`th symbol is a
+/// dash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Symbols {
+    dashes: u8,
+    len: u8,
+}
+
+impl Symbols {
+    fn from_pattern(pattern: &str) -> Self {
+        let mut symbols = Symbols::default();
+        for c in pattern.chars() {
+            symbols.push(c == '-');
+        }
+        symbols
+    }
+
+    fn push(&mut self, is_dash: bool) {
+        if self.len < MAX_SYMBOLS {
+            self.dashes |= u8::from(is_dash) << self.len;
+        }
+        self.len = self.len.saturating_add(1);
+    }
+
+    fn decode(&self) -> char {
+        TABLE
+            .iter()
+            .find(|(pattern, _)| Symbols::from_pattern(pattern) == *self)
+            .map(|&(_, c)| c)
+            .unwrap_or(MorseDecoder::UNKNOWN)
+    }
+}
+
+/// Decodes Morse code from the press and release times of one key.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug, Clone)]
+pub struct MorseDecoder {
+    /// The current estimate of the length of one unit, in milliseconds.
+    unit_ms: u32,
+    min_unit_ms: u32,
+    max_unit_ms: u32,
+    /// Whether the key was pressed during the last update.
+    pressed: bool,
+    /// When the key last changed state, or `None` before the first update.
+    changed_at: Option<u32>,
+    /// The symbols of the letter being keyed.
+    symbols: Symbols,
+    /// Whether a word gap should be emitted once the key has been released
+    /// for long enough.
+    word_pending: bool,
+}
+
+impl MorseDecoder {
+    /// The character produced for a sequence of dots and dashes that isn't in
+    /// the Morse code table.
+    pub const UNKNOWN: char = char::REPLACEMENT_CHARACTER;
+
+    /// Create a new decoder, starting with a unit length of `unit_ms`
+    /// milliseconds.
+    ///
+    /// The unit length will adapt to the operator, but stays within 10 ms and
+    /// 1 second (roughly 120 to 1 words per minute). Use
+    /// [`with_unit_limits()`](Self::with_unit_limits) to change that range.
+    pub fn new(unit_ms: u32) -> Self {
+        Self {
+            unit_ms: unit_ms.clamp(10, 1000),
+            min_unit_ms: 10,
+            max_unit_ms: 1000,
+            pressed: false,
+            changed_at: None,
+            symbols: Symbols::default(),
+            word_pending: false,
+        }
+    }
+
+    /// Limit how short or long the estimated unit length may become.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_ms` is 0 or greater than `max_ms`.
+    pub fn with_unit_limits(mut self, min_ms: u32, max_ms: u32) -> Self {
+        assert!(
+            min_ms > 0 && min_ms <= max_ms,
+            "invalid Morse unit length limits"
+        );
+        self.min_unit_ms = min_ms;
+        self.max_unit_ms = max_ms;
+        self.unit_ms = self.unit_ms.clamp(min_ms, max_ms);
+        self
+    }
+
+    /// The current estimate of the length of one unit (the length of a dot),
+    /// in milliseconds.
+    pub fn unit_ms(&self) -> u32 {
+        self.unit_ms
+    }
+
+    /// The estimated speed in words per minute, using the standard word
+    /// "PARIS" which is 50 units long.
+    ///
+    /// ```
+    /// use keypad::morse::MorseDecoder;
+    ///
+    /// assert_eq!(MorseDecoder::new(60).words_per_minute(), 20);
+    ///
+    /// // Very long units are slower than one word per minute.
+    /// let decoder = MorseDecoder::new(60).with_unit_limits(100_000_000, u32::MAX);
+    /// assert_eq!(decoder.words_per_minute(), 0);
+    /// ```
+    pub fn words_per_minute(&self) -> u32 {
+        (60_000 / (50 * u64::from(self.unit_ms))) as u32
+    }
+
+    /// Read the key and update the decoder, returning a character if one was
+    /// finished.
+    ///
+    /// The key is pressed if it's low, as with a `KeypadInput`.
+    pub fn poll<P: InputPin>(&mut self, key: &P, now_ms: u32) -> Result<Option<char>, P::Error> {
+        Ok(self.update(key.is_low()?, now_ms))
+    }
+
+    /// Update the decoder with the state of the key at time `now_ms`, and
+    /// return a character if one was finished.
+    ///
+    /// A space is returned at the end of each word. Letters that aren't in
+    /// the table are returned as [`UNKNOWN`](Self::UNKNOWN).
+    ///
+    /// The time is allowed to wrap around.
+    ///
+    /// ```
+    /// use keypad::morse::MorseDecoder;
+    ///
+    /// // The decoder expects 15 words per minute, but the operator keys at
+    /// // 20, with a unit length of 60 ms.
+    /// let mut decoder = MorseDecoder::new(80);
+    /// assert_eq!(decoder.words_per_minute(), 15);
+    ///
+    /// let mut pressed = Vec::new();
+    /// for word in [[".--.", ".-", ".-.", "..", "..."]; 2].iter() {
+    ///     for letter in word.iter() {
+    ///         for symbol in letter.chars() {
+    ///             let units = if symbol == '.' { 1 } else { 3 };
+    ///             pressed.extend(std::iter::repeat(true).take(units * 6));
+    ///             pressed.extend(std::iter::repeat(false).take(6));
+    ///         }
+    ///         pressed.extend(std::iter::repeat(false).take(12));
+    ///     }
+    ///     pressed.extend(std::iter::repeat(false).take(24));
+    /// }
+    ///
+    /// let mut text = String::new();
+    /// for (i, &is_pressed) in pressed.iter().enumerate() {
+    ///     text.extend(decoder.update(is_pressed, i as u32 * 10));
+    /// }
+    /// assert_eq!(text, "PARIS PARIS ");
+    /// assert_eq!(decoder.unit_ms(), 60);
+    /// assert_eq!(decoder.words_per_minute(), 20);
+    /// ```
+    pub fn update(&mut self, pressed: bool, now_ms: u32) -> Option<char> {
+        let changed_at = match self.changed_at {
+            Some(changed_at) => changed_at,
+            None => {
+                self.pressed = pressed;
+                self.changed_at = Some(now_ms);
+                return None;
+            }
+        };
+        let elapsed = now_ms.wrapping_sub(changed_at);
+
+        if pressed != self.pressed {
+            self.pressed = pressed;
+            self.changed_at = Some(now_ms);
+            if !pressed {
+                self.end_mark(elapsed);
+            }
+            return None;
+        }
+
+        if pressed {
+            return None;
+        }
+        if self.symbols.len > 0 && elapsed > self.unit_ms.saturating_mul(2) {
+            let letter = self.symbols.decode();
+            self.symbols = Symbols::default();
+            self.word_pending = true;
+            return Some(letter);
+        }
+        if self.word_pending && elapsed > self.unit_ms.saturating_mul(5) {
+            self.word_pending = false;
+            return Some(' ');
+        }
+        None
+    }
+
+    /// Forget the letter being keyed, without decoding it.
+    pub fn reset(&mut self) {
+        self.symbols = Symbols::default();
+        self.word_pending = false;
+    }
+
+    /// Classify a press that lasted `duration` ms, and adapt the unit length.
+    fn end_mark(&mut self, duration: u32) {
+        let is_dash = duration >= self.unit_ms.saturating_mul(2);
+        self.symbols.push(is_dash);
+
+        let measured_unit = if is_dash { duration / 3 } else { duration };
+        // Move a quarter of the way towards the new measurement, so a single
+        // sloppy symbol doesn't throw off the estimate.
+        let unit = (3 * u64::from(self.unit_ms) + u64::from(measured_unit)) / 4;
+        self.unit_ms = (unit as u32).clamp(self.min_unit_ms, self.max_unit_ms);
+    }
+}
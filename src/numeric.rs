@@ -0,0 +1,406 @@
+//! Typing numbers like "12.5" on a keypad.
+//!
+//! [`NumericEntry`] is a small line editor for numbers. It takes
+//! [`EntryKey`]s, either directly or by looking up key events in a keymap, and
+//! keeps the text that has been typed so far so you can show it on a display.
+//! When the enter key is pressed, it parses the text and checks it against the
+//! allowed range.
+//!
+//! Values are integers. To enter fractional values, set the number of decimal
+//! places with [`with_decimals()`](NumericEntry::with_decimals), and the value
+//! will be returned in fixed-point: "12.5" with 2 decimal places is `1250`.
+//!
+//! ```
+//! use keypad::numeric::{telephone_keymap, EntryError, EntryKey, NumericEntry};
+//! use keypad::{KeyEvent, Position};
+//!
+//! // On a telephone keypad, use `*` for the decimal point and `#` for enter,
+//! // and `D` to delete the last character.
+//! let mut keymap = telephone_keymap(Some(EntryKey::DecimalPoint), Some(EntryKey::Enter));
+//! keymap[3][3] = Some(EntryKey::Backspace);
+//!
+//! let mut entry = NumericEntry::<8>::new()
+//!     .with_decimals(1)
+//!     .with_range(0, 1000);
+//!
+//! let presses = [(0, 0), (0, 1), (3, 0), (0, 2), (3, 3), (1, 1)];
+//! for &(row, column) in presses.iter() {
+//!     let event = KeyEvent::Pressed(Position::new(row, column));
+//!     assert_eq!(entry.update(event, &keymap), Ok(None));
+//! }
+//! assert_eq!(entry.as_str(), "12.5");
+//!
+//! let enter = KeyEvent::Pressed(Position::new(3, 2));
+//! assert_eq!(entry.update(enter, &keymap), Ok(Some(125)));
+//! assert_eq!(entry.as_str(), "");
+//!
+//! // Values outside of the range are rejected, and the text is kept so it can
+//! // be corrected.
+//! entry.press(EntryKey::Digit(2)).unwrap();
+//! entry.press(EntryKey::Digit(0)).unwrap();
+//! entry.press(EntryKey::Digit(0)).unwrap();
+//! assert_eq!(entry.press(EntryKey::Enter), Err(EntryError::OutOfRange));
+//! assert_eq!(entry.as_str(), "200");
+//! ```
+
+use crate::KeyEvent;
+
+/// An editing action for a `NumericEntry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKey {
+    /// Type a digit from 0 to 9.
+    Digit(u8),
+    /// Type the decimal point.
+    DecimalPoint,
+    /// Switch between positive and negative.
+    Sign,
+    /// Delete the last character.
+    Backspace,
+    /// Delete all of the text.
+    Clear,
+    /// Finish typing, and parse the value.
+    Enter,
+}
+
+/// The reasons a key can be rejected by a `NumericEntry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryError {
+    /// There's no room left for another character.
+    Full,
+    /// A digit greater than 9 was typed.
+    InvalidDigit,
+    /// The decimal point was typed twice, or the entry doesn't use decimal
+    /// places.
+    DecimalPointNotAllowed,
+    /// There are already as many digits after the decimal point as the entry
+    /// uses.
+    TooManyDecimals,
+    /// The sign key was pressed, but the allowed range has no negative values.
+    SignNotAllowed,
+    /// Enter was pressed without typing any digits.
+    Empty,
+    /// The value is outside of the allowed range.
+    OutOfRange,
+}
+
+/// A keymap for a standard 4x4 telephone keypad, with the digits in their
+/// usual places and the given actions for `*` and `#`. The letter keys in the
+/// last column don't have an action, but you can add your own.
+pub fn telephone_keymap(
+    star: Option<EntryKey>,
+    hash: Option<EntryKey>,
+) -> [[Option<EntryKey>; 4]; 4] {
+    let digit = |d| Some(EntryKey::Digit(d));
+    [
+        [digit(1), digit(2), digit(3), None],
+        [digit(4), digit(5), digit(6), None],
+        [digit(7), digit(8), digit(9), None],
+        [star, digit(0), hash, None],
+    ]
+}
+
+/// A line editor for entering a number of up to `N` characters, including
+/// the sign and the decimal point.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug, Clone)]
+pub struct NumericEntry<const N: usize> {
+    text: [u8; N],
+    len: usize,
+    decimals: u8,
+    min: i32,
+    max: i32,
+}
+
+impl<const N: usize> NumericEntry<N> {
+    /// Create a new, empty entry for integers, allowing any `i32` value.
+    pub fn new() -> Self {
+        Self {
+            text: [0; N],
+            len: 0,
+            decimals: 0,
+            min: i32::MIN,
+            max: i32::MAX,
+        }
+    }
+
+    /// Allow this many digits after the decimal point. Values will be scaled
+    /// by 10 to the power of `decimals`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decimals` is greater than 9, because the scaled values would
+    /// not fit in an `i32`.
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        assert!(decimals <= 9, "too many decimal places for an i32");
+        self.decimals = decimals;
+        self
+    }
+
+    /// Only accept values from `min` to `max`, inclusive. With decimal places,
+    /// the limits are scaled the same way as the values.
+    ///
+    /// The sign key is only allowed if `min` is negative.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn with_range(mut self, min: i32, max: i32) -> Self {
+        assert!(min <= max, "invalid range for numeric entry");
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// The text typed so far.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII characters are ever stored.
+        core::str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+
+    /// Has nothing been typed?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Delete all of the text.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Look up the key for a press event in the keymap, and handle it.
+    /// Release events, and keys without an entry in the keymap, are ignored.
+    ///
+    /// Returns the value when enter is pressed, or an error if the key was
+    /// rejected.
+    pub fn update<const ROWS: usize, const COLUMNS: usize>(
+        &mut self,
+        event: KeyEvent,
+        keymap: &[[Option<EntryKey>; COLUMNS]; ROWS],
+    ) -> Result<Option<i32>, EntryError> {
+        if !event.is_press() {
+            return Ok(None);
+        }
+        let position = event.position();
+        let key = keymap
+            .get(position.row)
+            .and_then(|row| row.get(position.column))
+            .copied()
+            .flatten();
+        match key {
+            Some(key) => self.press(key),
+            None => Ok(None),
+        }
+    }
+
+    /// Handle one key.
+    ///
+    /// Returns the value when enter is pressed, or an error if the key was
+    /// rejected. The text is left unchanged when there's an error, and is
+    /// cleared after a value is successfully entered.
+    ///
+    /// ```
+    /// use keypad::numeric::{EntryError, EntryKey, NumericEntry};
+    ///
+    /// let mut entry = NumericEntry::<5>::new().with_decimals(1).with_range(-200, 200);
+    /// assert_eq!(entry.press(EntryKey::Digit(10)), Err(EntryError::InvalidDigit));
+    /// entry.press(EntryKey::Digit(1)).unwrap();
+    /// entry.press(EntryKey::Digit(2)).unwrap();
+    ///
+    /// // The sign can be toggled at any point, and goes at the front.
+    /// entry.press(EntryKey::Sign).unwrap();
+    /// assert_eq!(entry.as_str(), "-12");
+    /// entry.press(EntryKey::Sign).unwrap();
+    /// assert_eq!(entry.as_str(), "12");
+    /// entry.press(EntryKey::Sign).unwrap();
+    ///
+    /// entry.press(EntryKey::DecimalPoint).unwrap();
+    /// assert_eq!(entry.press(EntryKey::DecimalPoint), Err(EntryError::DecimalPointNotAllowed));
+    /// assert_eq!(entry.press(EntryKey::Digit(5)), Ok(None));
+    /// assert_eq!(entry.as_str(), "-12.5");
+    /// ```
+    ///
+    /// The text includes the sign and the decimal point, so they count
+    /// towards the `N` characters too.
+    ///
+    /// ```
+    /// use keypad::numeric::{EntryError, EntryKey, NumericEntry};
+    ///
+    /// let mut entry = NumericEntry::<3>::new();
+    /// for digit in 1..=3 {
+    ///     entry.press(EntryKey::Digit(digit)).unwrap();
+    /// }
+    /// assert_eq!(entry.press(EntryKey::Digit(4)), Err(EntryError::Full));
+    /// assert_eq!(entry.press(EntryKey::Sign), Err(EntryError::Full));
+    /// entry.press(EntryKey::Backspace).unwrap();
+    /// entry.press(EntryKey::Sign).unwrap();
+    /// assert_eq!(entry.as_str(), "-12");
+    ///
+    /// // There's room for a second decimal, but the entry only uses one.
+    /// let mut entry = NumericEntry::<8>::new().with_decimals(1);
+    /// entry.press(EntryKey::DecimalPoint).unwrap();
+    /// entry.press(EntryKey::Digit(2)).unwrap();
+    /// assert_eq!(entry.press(EntryKey::Digit(5)), Err(EntryError::TooManyDecimals));
+    ///
+    /// // Integers have no decimal point, and unsigned ranges have no sign.
+    /// let mut entry = NumericEntry::<8>::new().with_range(0, 100);
+    /// assert_eq!(entry.press(EntryKey::DecimalPoint), Err(EntryError::DecimalPointNotAllowed));
+    /// assert_eq!(entry.press(EntryKey::Sign), Err(EntryError::SignNotAllowed));
+    /// ```
+    ///
+    /// Backspace deletes the sign too, once the digits after it are gone. A
+    /// sign or a decimal point on its own isn't a value.
+    ///
+    /// ```
+    /// use keypad::numeric::{EntryError, EntryKey, NumericEntry};
+    ///
+    /// let mut entry = NumericEntry::<8>::new().with_decimals(2);
+    /// entry.press(EntryKey::Sign).unwrap();
+    /// entry.press(EntryKey::Digit(3)).unwrap();
+    /// entry.press(EntryKey::Backspace).unwrap();
+    /// assert_eq!(entry.as_str(), "-");
+    /// assert_eq!(entry.press(EntryKey::Enter), Err(EntryError::Empty));
+    /// entry.press(EntryKey::Backspace).unwrap();
+    /// assert!(entry.is_empty());
+    ///
+    /// entry.press(EntryKey::DecimalPoint).unwrap();
+    /// assert_eq!(entry.press(EntryKey::Enter), Err(EntryError::Empty));
+    /// entry.press(EntryKey::Digit(5)).unwrap();
+    /// assert_eq!(entry.press(EntryKey::Enter), Ok(Some(50)));
+    /// ```
+    pub fn press(&mut self, key: EntryKey) -> Result<Option<i32>, EntryError> {
+        match key {
+            EntryKey::Digit(digit) => {
+                if digit > 9 {
+                    return Err(EntryError::InvalidDigit);
+                }
+                if self.decimal_digits().map_or(false, |n| n >= self.decimals) {
+                    return Err(EntryError::TooManyDecimals);
+                }
+                self.push(b'0' + digit)?;
+            }
+            EntryKey::DecimalPoint => {
+                if self.decimals == 0 || self.decimal_digits().is_some() {
+                    return Err(EntryError::DecimalPointNotAllowed);
+                }
+                self.push(b'.')?;
+            }
+            EntryKey::Sign => {
+                if self.min >= 0 {
+                    return Err(EntryError::SignNotAllowed);
+                }
+                self.toggle_sign()?;
+            }
+            EntryKey::Backspace => self.len = self.len.saturating_sub(1),
+            EntryKey::Clear => self.clear(),
+            EntryKey::Enter => {
+                let value = self.value()?;
+                self.clear();
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the text typed so far, without clearing it.
+    ///
+    /// ```
+    /// use keypad::numeric::{EntryError, EntryKey, NumericEntry};
+    ///
+    /// fn parse(text: &str) -> Result<i32, EntryError> {
+    ///     let mut entry = NumericEntry::<12>::new();
+    ///     for c in text.bytes() {
+    ///         let key = match c {
+    ///             b'-' => EntryKey::Sign,
+    ///             _ => EntryKey::Digit(c - b'0'),
+    ///         };
+    ///         entry.press(key)?;
+    ///     }
+    ///     entry.value()
+    /// }
+    ///
+    /// assert_eq!(parse("2147483647"), Ok(i32::MAX));
+    /// assert_eq!(parse("2147483648"), Err(EntryError::OutOfRange));
+    /// assert_eq!(parse("-2147483648"), Ok(i32::MIN));
+    /// assert_eq!(parse("-2147483649"), Err(EntryError::OutOfRange));
+    /// assert_eq!(parse("99999999999"), Err(EntryError::OutOfRange));
+    /// // Leading zeros take up room, but don't change the value.
+    /// assert_eq!(parse("-00214748364"), Ok(-214748364));
+    /// assert_eq!(parse("-002147483648"), Err(EntryError::Full));
+    /// ```
+    pub fn value(&self) -> Result<i32, EntryError> {
+        let text = &self.text[..self.len];
+        let (negative, text) = match text.split_first() {
+            Some((b'-', rest)) => (true, rest),
+            _ => (false, text),
+        };
+        if !text.iter().any(u8::is_ascii_digit) {
+            return Err(EntryError::Empty);
+        }
+
+        let mut value: i64 = 0;
+        let mut decimals = 0;
+        let mut after_point = false;
+        for &c in text {
+            if c == b'.' {
+                after_point = true;
+                continue;
+            }
+            value = value * 10 + i64::from(c - b'0');
+            decimals += u8::from(after_point);
+            if value > i64::from(u32::MAX) {
+                return Err(EntryError::OutOfRange);
+            }
+        }
+        for _ in decimals..self.decimals {
+            value *= 10;
+        }
+        if negative {
+            value = -value;
+        }
+
+        if value < i64::from(self.min) || value > i64::from(self.max) {
+            return Err(EntryError::OutOfRange);
+        }
+        Ok(value as i32)
+    }
+
+    /// The number of digits after the decimal point, or `None` if it hasn't
+    /// been typed.
+    fn decimal_digits(&self) -> Option<u8> {
+        let text = &self.text[..self.len];
+        text.iter()
+            .position(|&c| c == b'.')
+            .map(|point| (text.len() - point - 1) as u8)
+    }
+
+    fn push(&mut self, c: u8) -> Result<(), EntryError> {
+        if self.len == N {
+            return Err(EntryError::Full);
+        }
+        self.text[self.len] = c;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn toggle_sign(&mut self) -> Result<(), EntryError> {
+        if self.len > 0 && self.text[0] == b'-' {
+            self.text.copy_within(1..self.len, 0);
+            self.len -= 1;
+        } else {
+            if self.len == N {
+                return Err(EntryError::Full);
+            }
+            self.text.copy_within(0..self.len, 1);
+            self.text[0] = b'-';
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for NumericEntry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,206 @@
+//! Keys with normally-closed switches.
+//!
+//! Every backend assumes that a key's switch closes when it's pressed. A
+//! [`PolarityMask`] records which keys work the other way round, and
+//! [`Polarized`] wraps any [`Scan`] to invert their readings, so the scanned
+//! state says which keys are really pressed.
+//!
+//! ```
+//! use keypad::expander::Pcf8574;
+//! use keypad::mock_hal::MockPcf8574;
+//! use keypad::polarity::Polarized;
+//! use keypad::{Polarity, Position, Scan};
+//!
+//! let expander = MockPcf8574::new(0x20);
+//! let keypad: Pcf8574<_, _, 2, 2> = Pcf8574::new(&expander, 0x20).unwrap();
+//!
+//! // The lid switch connects P1 and P3 until the lid is opened.
+//! let lid = Position::new(1, 1);
+//! let mut keypad = Polarized::new(keypad).with_polarity(lid, Polarity::NormallyClosed);
+//! expander.connect(1, 3);
+//! assert!(!keypad.scan().unwrap().any());
+//!
+//! expander.disconnect(1, 3);
+//! expander.connect(0, 2);
+//! let pressed: Vec<Position> = keypad.scan().unwrap().pressed_keys().collect();
+//! assert_eq!(pressed, [Position::new(0, 0), lid]);
+//! ```
+
+use crate::{KeyState, Position, Scan};
+
+/// Whether a key's switch is open or closed when the key isn't pressed.
+///
+/// Every key is treated as normally open unless told otherwise, so a key is
+/// pressed when its switch is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Polarity {
+    /// The switch closes when the key is pressed, so the input reads low
+    /// while it's pressed.
+    NormallyOpen,
+    /// The switch opens when the key is pressed, like many safety switches,
+    /// so the input reads low while it isn't pressed.
+    NormallyClosed,
+}
+
+impl Polarity {
+    /// Is a key with this polarity pressed, if its switch is `closed`?
+    pub fn is_pressed(self, closed: bool) -> bool {
+        match self {
+            Polarity::NormallyOpen => closed,
+            Polarity::NormallyClosed => !closed,
+        }
+    }
+}
+
+impl Default for Polarity {
+    fn default() -> Self {
+        Polarity::NormallyOpen
+    }
+}
+
+/// The polarity of every key in a keypad with `ROWS` rows and `COLUMNS`
+/// columns.
+///
+/// ```
+/// use keypad::{KeyState, Polarity, PolarityMask, Position};
+///
+/// let mut mask = PolarityMask::<1, 2>::new();
+/// mask.set(Position::new(0, 1), Polarity::NormallyClosed);
+///
+/// // Both switches are closed, but only the first key is pressed.
+/// let mut closed = KeyState::new();
+/// closed.set(Position::new(0, 0), true);
+/// closed.set(Position::new(0, 1), true);
+/// let pressed: Vec<Position> = mask.apply(closed).pressed_keys().collect();
+/// assert_eq!(pressed, [Position::new(0, 0)]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolarityMask<const ROWS: usize, const COLUMNS: usize> {
+    polarity: [[Polarity; COLUMNS]; ROWS],
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> PolarityMask<ROWS, COLUMNS> {
+    /// Create a new `PolarityMask` with every key normally open.
+    pub const fn new() -> Self {
+        Self {
+            polarity: [[Polarity::NormallyOpen; COLUMNS]; ROWS],
+        }
+    }
+
+    /// The polarity of the key at this position. Positions outside of the
+    /// keypad are normally open.
+    pub fn polarity(&self, position: Position) -> Polarity {
+        self.polarity
+            .get(position.row)
+            .and_then(|row| row.get(position.column))
+            .copied()
+            .unwrap_or(Polarity::NormallyOpen)
+    }
+
+    /// Set the polarity of the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn set(&mut self, position: Position, polarity: Polarity) {
+        self.polarity[position.row][position.column] = polarity;
+    }
+
+    /// Is the key at this position pressed, if its switch is `closed`?
+    pub fn is_pressed(&self, position: Position, closed: bool) -> bool {
+        self.polarity(position).is_pressed(closed)
+    }
+
+    /// Turn a state that says which switches are closed into one that says
+    /// which keys are pressed.
+    pub fn apply(&self, closed: KeyState<ROWS, COLUMNS>) -> KeyState<ROWS, COLUMNS> {
+        let mut state = KeyState::new();
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let position = Position::new(row, column);
+                state.set(
+                    position,
+                    self.is_pressed(position, closed.is_pressed(position)),
+                );
+            }
+        }
+        state
+    }
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> Default for PolarityMask<ROWS, COLUMNS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A keypad whose normally-closed keys are inverted when it's scanned.
+#[derive(Debug)]
+pub struct Polarized<S, const ROWS: usize, const COLUMNS: usize> {
+    keypad: S,
+    mask: PolarityMask<ROWS, COLUMNS>,
+}
+
+impl<S, E, const ROWS: usize, const COLUMNS: usize> Polarized<S, ROWS, COLUMNS>
+where
+    S: Scan<ROWS, COLUMNS, Error = E>,
+{
+    /// Wrap a keypad, with every key normally open.
+    pub fn new(keypad: S) -> Self {
+        Self {
+            keypad,
+            mask: PolarityMask::new(),
+        }
+    }
+
+    /// Set the polarity of the key at this position, and return the keypad.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn with_polarity(mut self, position: Position, polarity: Polarity) -> Self {
+        self.set_polarity(position, polarity);
+        self
+    }
+
+    /// Set the polarity of the key at this position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn set_polarity(&mut self, position: Position, polarity: Polarity) {
+        self.mask.set(position, polarity);
+    }
+
+    /// The polarity of every key.
+    pub fn mask(&self) -> &PolarityMask<ROWS, COLUMNS> {
+        &self.mask
+    }
+
+    /// The wrapped keypad.
+    pub fn keypad(&self) -> &S {
+        &self.keypad
+    }
+
+    /// Give back the wrapped keypad.
+    pub fn release(self) -> S {
+        self.keypad
+    }
+
+    /// Scan the keypad, and invert the readings of normally-closed keys.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Ok(self.mask.apply(self.keypad.scan()?))
+    }
+}
+
+impl<S, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Polarized<S, ROWS, COLUMNS>
+where
+    S: Scan<ROWS, COLUMNS, Error = E>,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Polarized::scan(self)
+    }
+}
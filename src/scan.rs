@@ -0,0 +1,219 @@
+//! Reading the whole keypad at once, and turning those readings into key
+//! press and release events.
+//!
+//! A `KeypadInput` is the right tool when you only care about one key at a
+//! time. When you want to know about every key, it's easier to take a
+//! [`KeyState`] snapshot of the entire keypad with [`Scan::scan()`], and then
+//! compare consecutive snapshots with an [`EventTracker`] to find out which
+//! keys were pressed or released in between.
+//!
+//! ```
+//! use keypad::{EventTracker, KeyEvent, KeyState, Position};
+//!
+//! let mut tracker = EventTracker::<2, 3>::new();
+//!
+//! let mut state = KeyState::new();
+//! state.set(Position::new(1, 2), true);
+//!
+//! let mut events = tracker.update(state);
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(1, 2))));
+//! assert_eq!(events.next(), None);
+//!
+//! let mut events = tracker.update(KeyState::new());
+//! assert_eq!(events.next(), Some(KeyEvent::Released(Position::new(1, 2))));
+//! assert_eq!(events.next(), None);
+//! ```
+
+/// The row and column of one key in a keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    /// The index of the key's row, starting from 0.
+    pub row: usize,
+    /// The index of the key's column, starting from 0.
+    pub column: usize,
+}
+
+impl Position {
+    /// Create a new `Position`.
+    pub const fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+/// A change in the state of one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyEvent {
+    /// The key at this position was pressed.
+    Pressed(Position),
+    /// The key at this position was released.
+    Released(Position),
+}
+
+impl KeyEvent {
+    /// The position of the key that changed.
+    pub fn position(&self) -> Position {
+        match *self {
+            KeyEvent::Pressed(position) | KeyEvent::Released(position) => position,
+        }
+    }
+
+    /// Is this a key press, rather than a release?
+    pub fn is_press(&self) -> bool {
+        matches!(self, KeyEvent::Pressed(_))
+    }
+}
+
+/// A snapshot of which keys in a keypad with `ROWS` rows and `COLUMNS`
+/// columns were pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyState<const ROWS: usize, const COLUMNS: usize> {
+    pressed: [[bool; COLUMNS]; ROWS],
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> KeyState<ROWS, COLUMNS> {
+    /// Create a new `KeyState` with every key released.
+    pub const fn new() -> Self {
+        Self {
+            pressed: [[false; COLUMNS]; ROWS],
+        }
+    }
+
+    /// The number of rows in the keypad.
+    pub const fn rows(&self) -> usize {
+        ROWS
+    }
+
+    /// The number of columns in the keypad.
+    pub const fn columns(&self) -> usize {
+        COLUMNS
+    }
+
+    /// Is the key at this position pressed? Positions outside of the keypad
+    /// are never pressed.
+    pub fn is_pressed(&self, position: Position) -> bool {
+        self.pressed
+            .get(position.row)
+            .and_then(|row| row.get(position.column))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Set whether the key at this position is pressed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the keypad.
+    pub fn set(&mut self, position: Position, pressed: bool) {
+        self.pressed[position.row][position.column] = pressed;
+    }
+
+    /// The number of keys that are pressed.
+    pub fn count(&self) -> usize {
+        self.pressed_keys().count()
+    }
+
+    /// Is any key pressed?
+    pub fn any(&self) -> bool {
+        self.pressed_keys().next().is_some()
+    }
+
+    /// Iterate over the positions of all pressed keys, row by row.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Position> + '_ {
+        self.pressed.iter().enumerate().flat_map(|(row, columns)| {
+            columns
+                .iter()
+                .enumerate()
+                .filter(|(_, pressed)| **pressed)
+                .map(move |(column, _)| Position::new(row, column))
+        })
+    }
+
+    /// Iterate over the events that would turn the `previous` state into this
+    /// one, row by row.
+    pub fn events_since(&self, previous: &Self) -> Events<ROWS, COLUMNS> {
+        Events {
+            previous: *previous,
+            current: *self,
+            index: 0,
+        }
+    }
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> Default for KeyState<ROWS, COLUMNS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An iterator over the differences between two `KeyState`s.
+///
+/// This is returned by [`KeyState::events_since()`] and
+/// [`EventTracker::update()`].
+#[derive(Debug, Clone)]
+pub struct Events<const ROWS: usize, const COLUMNS: usize> {
+    previous: KeyState<ROWS, COLUMNS>,
+    current: KeyState<ROWS, COLUMNS>,
+    index: usize,
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> Iterator for Events<ROWS, COLUMNS> {
+    type Item = KeyEvent;
+
+    fn next(&mut self) -> Option<KeyEvent> {
+        while self.index < ROWS * COLUMNS {
+            let position = Position::new(self.index / COLUMNS, self.index % COLUMNS);
+            self.index += 1;
+            match (
+                self.previous.is_pressed(position),
+                self.current.is_pressed(position),
+            ) {
+                (false, true) => return Some(KeyEvent::Pressed(position)),
+                (true, false) => return Some(KeyEvent::Released(position)),
+                _ => (),
+            }
+        }
+        None
+    }
+}
+
+/// Remembers the last `KeyState` it saw, so it can report which keys changed
+/// in each new one.
+#[derive(Debug, Clone, Default)]
+pub struct EventTracker<const ROWS: usize, const COLUMNS: usize> {
+    previous: KeyState<ROWS, COLUMNS>,
+}
+
+impl<const ROWS: usize, const COLUMNS: usize> EventTracker<ROWS, COLUMNS> {
+    /// Create a new `EventTracker` that assumes every key starts out released.
+    pub const fn new() -> Self {
+        Self {
+            previous: KeyState::new(),
+        }
+    }
+
+    /// The most recent state passed to `update()`.
+    pub fn state(&self) -> &KeyState<ROWS, COLUMNS> {
+        &self.previous
+    }
+
+    /// Store the new state, and iterate over the events since the previous
+    /// one.
+    pub fn update(&mut self, current: KeyState<ROWS, COLUMNS>) -> Events<ROWS, COLUMNS> {
+        let events = current.events_since(&self.previous);
+        self.previous = current;
+        events
+    }
+}
+
+/// Something that can read the state of every key in a keypad at once.
+///
+/// This is implemented by the structs generated with `keypad_struct!()`, so
+/// code that only needs key states can be written without knowing how the
+/// keypad is wired.
+pub trait Scan<const ROWS: usize, const COLUMNS: usize> {
+    /// The error that can occur while reading the keypad.
+    type Error;
+
+    /// Read the state of every key.
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, Self::Error>;
+}
@@ -0,0 +1,247 @@
+//! Keypads scanned by a TCA8418 keypad scanner.
+//!
+//! The TCA8418 scans a matrix of up to 8 rows and 10 columns by itself,
+//! debounces it, and queues a key event in a FIFO whenever a key is pressed
+//! or released. The [`Tca8418`] driver configures it over I2C and decodes
+//! those events into [`KeyEvent`]s. It also implements [`Scan`], by keeping
+//! track of which keys are pressed, so it can replace a GPIO keypad without
+//! changing the code that uses it.
+//!
+//! ```
+//! use keypad::mock_hal::MockTca8418;
+//! use keypad::tca8418::Tca8418;
+//! use keypad::{EventTracker, KeyEvent, Position};
+//!
+//! let mut keypad: Tca8418<_, 4, 3> = Tca8418::new(MockTca8418::new(0x34), 0x34).unwrap();
+//! assert!(!keypad.i2c().interrupt());
+//!
+//! keypad.i2c().press(2, 1);
+//! assert!(keypad.i2c().interrupt());
+//! assert_eq!(
+//!     keypad.read_event().unwrap(),
+//!     Some(KeyEvent::Pressed(Position::new(2, 1)))
+//! );
+//! assert_eq!(keypad.read_event().unwrap(), None);
+//!
+//! // The same events can be found by scanning, like any other keypad.
+//! let mut tracker = EventTracker::new();
+//! keypad.i2c().press(0, 0);
+//! keypad.i2c().release(2, 1);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(0, 0))));
+//! assert_eq!(events.next(), None);
+//! assert!(!keypad.i2c().interrupt());
+//! ```
+
+use crate::{KeyEvent, KeyState, Position, Scan};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+const CFG: u8 = 0x01;
+const INT_STAT: u8 = 0x02;
+const KEY_LCK_EC: u8 = 0x03;
+const KEY_EVENT_A: u8 = 0x04;
+const KP_GPIO1: u8 = 0x1D;
+const KP_GPIO2: u8 = 0x1E;
+const KP_GPIO3: u8 = 0x1F;
+
+const CFG_OVR_FLOW_IEN: u8 = 0x08;
+const CFG_KE_IEN: u8 = 0x01;
+const OVR_FLOW_INT: u8 = 0x08;
+const K_INT: u8 = 0x01;
+
+/// The interrupt status of a TCA8418.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Status {
+    /// The number of events waiting in the FIFO.
+    pub queued: u8,
+    /// Was a key event queued since the status was last cleared?
+    pub key_event: bool,
+    /// Did the FIFO overflow since the status was last cleared? Some events
+    /// were lost if it did.
+    pub overflow: bool,
+}
+
+/// A keypad matrix of `ROWS` rows and `COLUMNS` columns, connected to rows
+/// ROW0, ROW1, ... and columns COL0, COL1, ... of a TCA8418.
+///
+/// The interrupt output is asserted when a key event is queued or the FIFO
+/// overflows, and it's deasserted again by [`scan()`](Tca8418::scan) or
+/// [`clear_status()`](Tca8418::clear_status).
+#[derive(Debug)]
+pub struct Tca8418<I2C, const ROWS: usize, const COLUMNS: usize> {
+    i2c: I2C,
+    address: u8,
+    state: KeyState<ROWS, COLUMNS>,
+    overflowed: bool,
+}
+
+impl<I2C, E, const ROWS: usize, const COLUMNS: usize> Tca8418<I2C, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Create a new driver for the scanner at this address, and configure
+    /// its keypad size and interrupts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 8 rows or more than 10 columns.
+    pub fn new(i2c: I2C, address: u8) -> Result<Self, E> {
+        assert!(
+            ROWS <= 8 && COLUMNS <= 10,
+            "too many rows or columns for a TCA8418"
+        );
+        let mut keypad = Self {
+            i2c,
+            address,
+            state: KeyState::new(),
+            overflowed: false,
+        };
+        let columns = (1u16 << COLUMNS) - 1;
+        keypad.write(KP_GPIO1, ((1u16 << ROWS) - 1) as u8)?;
+        keypad.write(KP_GPIO2, columns as u8)?;
+        keypad.write(KP_GPIO3, (columns >> 8) as u8)?;
+        keypad.write(CFG, CFG_KE_IEN | CFG_OVR_FLOW_IEN)?;
+        keypad.clear_status()?;
+        Ok(keypad)
+    }
+
+    /// The I2C bus.
+    pub fn i2c(&self) -> &I2C {
+        &self.i2c
+    }
+
+    /// Give back the I2C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Read the interrupt status.
+    pub fn status(&mut self) -> Result<Status, E> {
+        let status = self.read(INT_STAT)?;
+        Ok(Status {
+            queued: self.read(KEY_LCK_EC)? & 0x0F,
+            key_event: status & K_INT != 0,
+            overflow: status & OVR_FLOW_INT != 0,
+        })
+    }
+
+    /// Clear the key event and overflow interrupts.
+    ///
+    /// The key event interrupt is asserted again straight away if there are
+    /// still events in the FIFO.
+    ///
+    /// ```
+    /// use keypad::mock_hal::MockTca8418;
+    /// use keypad::tca8418::Tca8418;
+    ///
+    /// let mut keypad: Tca8418<_, 1, 1> = Tca8418::new(MockTca8418::new(0x34), 0x34).unwrap();
+    /// keypad.i2c().press(0, 0);
+    /// keypad.clear_status().unwrap();
+    /// assert!(keypad.i2c().interrupt());
+    ///
+    /// keypad.read_event().unwrap();
+    /// keypad.clear_status().unwrap();
+    /// assert!(!keypad.i2c().interrupt());
+    /// ```
+    pub fn clear_status(&mut self) -> Result<(), E> {
+        self.write(INT_STAT, K_INT | OVR_FLOW_INT)
+    }
+
+    /// Take the oldest event out of the FIFO, or return `None` if it's
+    /// empty.
+    ///
+    /// Events for keys outside of the keypad, like general-purpose input
+    /// events, are skipped. The event is also applied to the state returned
+    /// by the next [`scan()`](Tca8418::scan).
+    ///
+    /// ```
+    /// use keypad::mock_hal::MockTca8418;
+    /// use keypad::tca8418::Tca8418;
+    /// use keypad::{KeyEvent, Position};
+    ///
+    /// let mut keypad: Tca8418<_, 1, 1> = Tca8418::new(MockTca8418::new(0x34), 0x34).unwrap();
+    /// keypad.i2c().press(0, 0);
+    /// let event = keypad.read_event().unwrap();
+    /// assert_eq!(event, Some(KeyEvent::Pressed(Position::new(0, 0))));
+    ///
+    /// // The key is still held, so the scan still says it's pressed.
+    /// assert!(keypad.scan().unwrap().is_pressed(Position::new(0, 0)));
+    /// ```
+    pub fn read_event(&mut self) -> Result<Option<KeyEvent>, E> {
+        loop {
+            let event = self.read(KEY_EVENT_A)?;
+            let code = usize::from(event & 0x7F);
+            if code == 0 {
+                return Ok(None);
+            }
+            let position = Position::new((code - 1) / 10, (code - 1) % 10);
+            if position.row >= ROWS || position.column >= COLUMNS {
+                continue;
+            }
+            let pressed = event & 0x80 != 0;
+            self.state.set(position, pressed);
+            return Ok(Some(if pressed {
+                KeyEvent::Pressed(position)
+            } else {
+                KeyEvent::Released(position)
+            }));
+        }
+    }
+
+    /// Has the FIFO overflowed during a scan since the last time this was
+    /// called?
+    ///
+    /// After an overflow, the state returned by [`scan()`](Tca8418::scan)
+    /// may be wrong until the affected keys are pressed or released again.
+    ///
+    /// ```
+    /// use keypad::mock_hal::MockTca8418;
+    /// use keypad::tca8418::Tca8418;
+    ///
+    /// let mut keypad: Tca8418<_, 1, 1> = Tca8418::new(MockTca8418::new(0x34), 0x34).unwrap();
+    /// for _ in 0..6 {
+    ///     keypad.i2c().press(0, 0);
+    ///     keypad.i2c().release(0, 0);
+    /// }
+    /// assert!(keypad.status().unwrap().overflow);
+    /// keypad.scan().unwrap();
+    /// assert!(keypad.take_overflow());
+    /// assert!(!keypad.take_overflow());
+    /// ```
+    pub fn take_overflow(&mut self) -> bool {
+        core::mem::replace(&mut self.overflowed, false)
+    }
+
+    /// Take every event out of the FIFO, clear the interrupts, and return
+    /// the state of every key after those events.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        let status = self.status()?;
+        self.overflowed |= status.overflow;
+        while self.read_event()?.is_some() {}
+        self.clear_status()?;
+        Ok(self.state)
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), E> {
+        self.i2c.write(self.address, &[register, value])
+    }
+
+    fn read(&mut self, register: u8) -> Result<u8, E> {
+        let mut buffer = [0];
+        self.i2c
+            .write_read(self.address, &[register], &mut buffer)?;
+        Ok(buffer[0])
+    }
+}
+
+impl<I2C, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Tca8418<I2C, ROWS, COLUMNS>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Tca8418::scan(self)
+    }
+}
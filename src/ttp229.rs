@@ -0,0 +1,142 @@
+//! Capacitive touch keypads driven by a TTP229.
+//!
+//! The TTP229 senses 8 or 16 touch keys by itself, and reports them over a
+//! two-wire serial interface: every falling edge of the clock line SCL puts
+//! the next key on the data line SDO. The [`Ttp229`] driver clocks in one bit
+//! per key, and implements [`Scan`], so a touch keypad can replace a
+//! membrane keypad without changing the code that uses it.
+//!
+//! Key 0 is at row 0, column 0, key 1 at row 0, column 1, and so on, row by
+//! row.
+//!
+//! ```
+//! use keypad::mock_hal::{MockTtp229, Wiring};
+//! use keypad::ttp229::Ttp229;
+//! use keypad::{EventTracker, KeyEvent, Position};
+//!
+//! static WIRING: Wiring = Wiring::new();
+//!
+//! let touch = MockTtp229::new();
+//! let mut keypad: Ttp229<_, _, _, 4, 4> =
+//!     Ttp229::new(touch.clock_pin(), touch.data_pin(), WIRING.delay());
+//! let mut tracker = EventTracker::new();
+//!
+//! touch.touch(6);
+//! let mut events = tracker.update(keypad.scan().unwrap());
+//! assert_eq!(events.next(), Some(KeyEvent::Pressed(Position::new(1, 2))));
+//! assert_eq!(events.next(), None);
+//! assert_eq!(touch.bits_clocked(), 16);
+//!
+//! // Each bit took two half periods of 2 µs.
+//! assert_eq!(WIRING.clock().now_us(), 64);
+//! ```
+
+use crate::{KeyState, Position, Scan};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A TTP229 touch keypad with `ROWS * COLUMNS` keys, read through its SCL
+/// and SDO pins.
+///
+/// The controller must be in its two-wire serial mode, and set up for 8 or 16
+/// keys to match `ROWS * COLUMNS`. By default, a key is touched when SDO is
+/// low, which is the controller's default output mode. The clock pin should
+/// be high to begin with, and stay high for at least 2 ms between scans, so
+/// that the controller starts each scan with key 0.
+#[derive(Debug)]
+pub struct Ttp229<C, D, T, const ROWS: usize, const COLUMNS: usize> {
+    clock: C,
+    data: D,
+    delay: T,
+    half_period_us: u8,
+    active_high: bool,
+}
+
+impl<C, D, T, E, const ROWS: usize, const COLUMNS: usize> Ttp229<C, D, T, ROWS, COLUMNS>
+where
+    C: OutputPin<Error = E>,
+    D: InputPin<Error = E>,
+    T: DelayUs<u8>,
+{
+    /// Create a new touch keypad, clocked with a half period of 2 µs.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless there are exactly 8 or 16 keys.
+    ///
+    /// ```should_panic
+    /// use keypad::mock_hal::{MockTtp229, Wiring};
+    /// use keypad::ttp229::Ttp229;
+    ///
+    /// static WIRING: Wiring = Wiring::new();
+    ///
+    /// // The controller always sends 8 or 16 bits, so a 3x4 keypad would
+    /// // drift out of step with it.
+    /// let touch = MockTtp229::new();
+    /// let _: Ttp229<_, _, _, 3, 4> =
+    ///     Ttp229::new(touch.clock_pin(), touch.data_pin(), WIRING.delay());
+    /// ```
+    pub fn new(clock: C, data: D, delay: T) -> Self {
+        assert!(
+            ROWS * COLUMNS == 8 || ROWS * COLUMNS == 16,
+            "a TTP229 has 8 or 16 keys"
+        );
+        Self {
+            clock,
+            data,
+            delay,
+            half_period_us: 2,
+            active_high: false,
+        }
+    }
+
+    /// Change how long the clock stays low, and high, for each bit.
+    pub fn with_half_period_us(mut self, half_period_us: u8) -> Self {
+        self.half_period_us = half_period_us;
+        self
+    }
+
+    /// Make a key touched when SDO is high instead, for controllers set up
+    /// with active-high outputs.
+    pub fn active_high(mut self) -> Self {
+        self.active_high = true;
+        self
+    }
+
+    /// Give back the clock pin, data pin and delay.
+    pub fn release(self) -> (C, D, T) {
+        (self.clock, self.data, self.delay)
+    }
+
+    /// Clock in one bit for every key, and return which keys are touched.
+    ///
+    /// The delay only covers the clock's half periods. The caller is
+    /// responsible for waiting at least 2 ms between scans, with the clock
+    /// high, so that the controller starts the next scan with key 0 again.
+    pub fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        let mut state = KeyState::new();
+        for key in 0..ROWS * COLUMNS {
+            self.clock.set_low()?;
+            self.delay.delay_us(self.half_period_us);
+            let touched = self.data.is_high()? == self.active_high;
+            self.clock.set_high()?;
+            self.delay.delay_us(self.half_period_us);
+            state.set(Position::new(key / COLUMNS, key % COLUMNS), touched);
+        }
+        Ok(state)
+    }
+}
+
+impl<C, D, T, E, const ROWS: usize, const COLUMNS: usize> Scan<ROWS, COLUMNS>
+    for Ttp229<C, D, T, ROWS, COLUMNS>
+where
+    C: OutputPin<Error = E>,
+    D: InputPin<Error = E>,
+    T: DelayUs<u8>,
+{
+    type Error = E;
+
+    fn scan(&mut self) -> Result<KeyState<ROWS, COLUMNS>, E> {
+        Ttp229::scan(self)
+    }
+}
@@ -0,0 +1,253 @@
+//! Velocity-sensing keys with two contacts each.
+//!
+//! The keys of a keyboard instrument usually close two contacts, one early
+//! in their travel and one at the bottom. The faster the key is struck, the
+//! less time passes between the two. A [`VelocityTracker`] pairs up two
+//! matrix positions for each logical key, notes the time each contact
+//! closes during a scan, and turns the time difference into a MIDI-style
+//! velocity from 1 to 127. Releases are timed the same way, from the second
+//! contact opening to the first one.
+//!
+//! ```
+//! use keypad::velocity::{DualContact, VelocityTracker};
+//! use keypad::{KeyEvent, KeyState, Position};
+//!
+//! // One key, whose contacts are at columns 0 and 1 of a 1x2 matrix.
+//! let contacts = [DualContact::new(
+//!     Position::new(0, 0),
+//!     Position::new(0, 0),
+//!     Position::new(0, 1),
+//! )];
+//! let mut tracker = VelocityTracker::<1, 2, 1>::new(contacts).with_range_us(1_000, 51_000);
+//!
+//! let mut state = KeyState::new();
+//! state.set(Position::new(0, 0), true);
+//! assert_eq!(tracker.update(&state, 10_000).next(), None);
+//!
+//! // The second contact closed 11 ms after the first.
+//! state.set(Position::new(0, 1), true);
+//! let press = tracker.update(&state, 21_000).next().unwrap();
+//! assert_eq!(press.event, KeyEvent::Pressed(Position::new(0, 0)));
+//! assert_eq!(press.velocity, 102);
+//! ```
+
+use crate::{KeyEvent, KeyState, Position};
+
+/// The time between contacts that gives the highest velocity, by default.
+pub const DEFAULT_FASTEST_US: u32 = 1_000;
+
+/// The time between contacts that gives the lowest velocity, by default.
+pub const DEFAULT_SLOWEST_US: u32 = 100_000;
+
+/// The two contacts of one logical key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DualContact {
+    /// The position reported in the key's events.
+    pub key: Position,
+    /// The matrix position of the contact that closes first.
+    pub first: Position,
+    /// The matrix position of the contact that closes second.
+    pub second: Position,
+}
+
+impl DualContact {
+    /// Create a new `DualContact`.
+    pub const fn new(key: Position, first: Position, second: Position) -> Self {
+        Self { key, first, second }
+    }
+}
+
+/// A key event, with the velocity of the key's movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VelocityEvent {
+    /// The press or release, at the logical key's position.
+    pub event: KeyEvent,
+    /// The velocity, from 1 for the slowest to 127 for the fastest.
+    pub velocity: u8,
+}
+
+/// Where one logical key is in its travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Up,
+    /// The first contact closed at this time.
+    Closing(u32),
+    Down,
+    /// The second contact opened at this time.
+    Opening(u32),
+}
+
+/// Turns scans of a matrix with `ROWS` rows and `COLUMNS` columns into
+/// velocity events for `KEYS` dual-contact keys.
+///
+/// A key is pressed when its second contact closes, and released when its
+/// first contact opens. A key whose first contact closes and opens again
+/// without the second one closing is never pressed. The timing is only as
+/// precise as the scan rate.
+#[derive(Debug, Clone)]
+pub struct VelocityTracker<const ROWS: usize, const COLUMNS: usize, const KEYS: usize> {
+    contacts: [DualContact; KEYS],
+    phases: [Phase; KEYS],
+    fastest_us: u32,
+    slowest_us: u32,
+}
+
+impl<const ROWS: usize, const COLUMNS: usize, const KEYS: usize>
+    VelocityTracker<ROWS, COLUMNS, KEYS>
+{
+    /// Create a new tracker, with every key released.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a contact is outside of the matrix.
+    pub fn new(contacts: [DualContact; KEYS]) -> Self {
+        for contact in &contacts {
+            for position in &[contact.first, contact.second] {
+                assert!(
+                    position.row < ROWS && position.column < COLUMNS,
+                    "contact outside of the matrix"
+                );
+            }
+        }
+        Self {
+            contacts,
+            phases: [Phase::Up; KEYS],
+            fastest_us: DEFAULT_FASTEST_US,
+            slowest_us: DEFAULT_SLOWEST_US,
+        }
+    }
+
+    /// Change the times between contacts that give velocity 127 and 1.
+    /// Times in between are scaled linearly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fastest_us` isn't less than `slowest_us`.
+    pub fn with_range_us(mut self, fastest_us: u32, slowest_us: u32) -> Self {
+        assert!(
+            fastest_us < slowest_us,
+            "fastest_us must be less than slowest_us"
+        );
+        self.fastest_us = fastest_us;
+        self.slowest_us = slowest_us;
+        self
+    }
+
+    /// The velocity for this time between contacts.
+    pub fn velocity(&self, elapsed_us: u32) -> u8 {
+        let elapsed = elapsed_us.clamp(self.fastest_us, self.slowest_us) - self.fastest_us;
+        let range = self.slowest_us - self.fastest_us;
+        (127 - u64::from(elapsed) * 126 / u64::from(range)) as u8
+    }
+
+    /// Is this logical key pressed?
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is `KEYS` or more.
+    pub fn is_pressed(&self, key: usize) -> bool {
+        matches!(self.phases[key], Phase::Down | Phase::Opening(_))
+    }
+
+    /// Release every key, without reporting any events.
+    pub fn reset(&mut self) {
+        self.phases = [Phase::Up; KEYS];
+    }
+
+    /// Update every key from a scan taken at time `now_us`, and iterate over
+    /// the presses and releases it caused, in key order.
+    ///
+    /// The time can wrap around, as long as scans are less than about 71
+    /// minutes apart.
+    ///
+    /// ```
+    /// use keypad::velocity::{DualContact, VelocityTracker};
+    /// use keypad::{KeyEvent, KeyState, Position};
+    ///
+    /// let (first, second) = (Position::new(0, 0), Position::new(0, 1));
+    /// let contacts = [DualContact::new(first, first, second)];
+    /// let mut tracker = VelocityTracker::<1, 2, 1>::new(contacts).with_range_us(1_000, 51_000);
+    /// let mut state = KeyState::new();
+    ///
+    /// // Both contacts closed between two scans, so the key was as fast as
+    /// // it could be.
+    /// state.set(first, true);
+    /// state.set(second, true);
+    /// let press = tracker.update(&state, 0).next().unwrap();
+    /// assert_eq!(press.event, KeyEvent::Pressed(first));
+    /// assert_eq!(press.velocity, 127);
+    ///
+    /// // The second contact opens, but the key goes back down before the
+    /// // first one does, so it's still the same press.
+    /// state.set(second, false);
+    /// assert_eq!(tracker.update(&state, 10_000).next(), None);
+    /// state.set(second, true);
+    /// assert_eq!(tracker.update(&state, 12_000).next(), None);
+    /// assert!(tracker.is_pressed(0));
+    ///
+    /// // The release is timed from the second contact opening to the first.
+    /// state.set(second, false);
+    /// assert_eq!(tracker.update(&state, 20_000).next(), None);
+    /// state.set(first, false);
+    /// let release = tracker.update(&state, 46_000).next().unwrap();
+    /// assert_eq!(release.event, KeyEvent::Released(first));
+    /// assert_eq!(release.velocity, 64);
+    /// assert!(!tracker.is_pressed(0));
+    /// ```
+    pub fn update(&mut self, state: &KeyState<ROWS, COLUMNS>, now_us: u32) -> VelocityEvents<KEYS> {
+        let mut events = [None; KEYS];
+        for (index, contact) in self.contacts.iter().enumerate() {
+            let first = state.is_pressed(contact.first);
+            let second = state.is_pressed(contact.second);
+            let (phase, elapsed) = match (self.phases[index], first, second) {
+                (Phase::Up, _, true) => (Phase::Down, Some(0)),
+                (Phase::Up, true, false) => (Phase::Closing(now_us), None),
+                (Phase::Closing(since), _, true) => (Phase::Down, Some(now_us.wrapping_sub(since))),
+                (Phase::Closing(_), false, false) => (Phase::Up, None),
+                (Phase::Down, false, false) => (Phase::Up, Some(0)),
+                (Phase::Down, true, false) => (Phase::Opening(now_us), None),
+                (Phase::Opening(since), false, false) => {
+                    (Phase::Up, Some(now_us.wrapping_sub(since)))
+                }
+                (Phase::Opening(_), _, true) => (Phase::Down, None),
+                (phase, _, _) => (phase, None),
+            };
+            if let Some(elapsed) = elapsed {
+                let event = if phase == Phase::Down {
+                    KeyEvent::Pressed(contact.key)
+                } else {
+                    KeyEvent::Released(contact.key)
+                };
+                events[index] = Some(VelocityEvent {
+                    event,
+                    velocity: self.velocity(elapsed),
+                });
+            }
+            self.phases[index] = phase;
+        }
+        VelocityEvents { events, index: 0 }
+    }
+}
+
+/// An iterator over the velocity events caused by one scan.
+///
+/// This is returned by [`VelocityTracker::update()`].
+#[derive(Debug, Clone)]
+pub struct VelocityEvents<const KEYS: usize> {
+    events: [Option<VelocityEvent>; KEYS],
+    index: usize,
+}
+
+impl<const KEYS: usize> Iterator for VelocityEvents<KEYS> {
+    type Item = VelocityEvent;
+
+    fn next(&mut self) -> Option<VelocityEvent> {
+        while self.index < KEYS {
+            self.index += 1;
+            if let Some(event) = self.events[self.index - 1] {
+                return Some(event);
+            }
+        }
+        None
+    }
+}